@@ -0,0 +1,277 @@
+//! Derive macros for `bitfields::FromBitFields`, `bitfields::IntoBitFields` and
+//! `bitfields::BitEnum`.
+//!
+//! Every member of the deriving struct must itself be a `BitField`, so its position and width
+//! come from the member's type and no attributes are needed. Members that overlap, or that are
+//! wider than their representation, fail to compile:
+//!
+//! ```compile_fail
+//! # use bitfields::BitField;
+//! # use bitfields_derive::FromBitFields;
+//! # use std::convert::TryFrom;
+//! #[derive(Debug)]
+//! struct Low;
+//! impl BitField for Low { const POS: usize = 0; const WIDTH: usize = 4; }
+//! # impl TryFrom<u8> for Low { type Error = (); fn try_from(_: u8) -> Result<Self, ()> { Ok(Low) } }
+//!
+//! #[derive(Debug)]
+//! struct Middle;
+//! impl BitField for Middle { const POS: usize = 2; const WIDTH: usize = 4; }
+//! # impl TryFrom<u8> for Middle { type Error = (); fn try_from(_: u8) -> Result<Self, ()> { Ok(Middle) } }
+//!
+//! #[derive(FromBitFields)]
+//! struct Header {
+//!     low: Low,
+//!     middle: Middle,
+//! }
+//! ```
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Expr, Fields, Index, LitStr, UnOp};
+
+/// Derives `bitfields::FromBitFields` by decoding each member with `get_as`.
+#[proc_macro_derive(FromBitFields)]
+pub fn derive_from_bit_fields(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_from_bit_fields(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derives `bitfields::IntoBitFields` by storing each member with `store`.
+#[proc_macro_derive(IntoBitFields)]
+pub fn derive_into_bit_fields(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_into_bit_fields(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derives `bitfields::BitEnum` for a fieldless enum from the discriminants of its variants.
+///
+/// A negative discriminant has no bit width, so signed reprs and negative discriminants fail
+/// to compile:
+///
+/// ```compile_fail
+/// # use bitfields_derive::BitEnum;
+/// #[derive(BitEnum)]
+/// #[repr(i8)]
+/// enum Neg {
+///     A = -1,
+///     B = 1,
+/// }
+/// ```
+#[proc_macro_derive(BitEnum)]
+pub fn derive_bit_enum(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_bit_enum(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn struct_fields(input: &DeriveInput) -> syn::Result<&Fields> {
+    match &input.data {
+        Data::Struct(data) => Ok(&data.fields),
+        _ => Err(syn::Error::new_spanned(
+            input,
+            "bitfields derives only support structs",
+        )),
+    }
+}
+
+/// A const block rejecting overlapping members, or members wider than their representation,
+/// when the generated impl is compiled.
+fn layout_check(input: &DeriveInput, fields: &Fields) -> TokenStream2 {
+    let tys = fields.iter().map(|f| &f.ty);
+    let message = LitStr::new(
+        &format!("members of `{}` overlap", input.ident),
+        input.ident.span(),
+    );
+    quote! {
+        const {
+            ::core::assert!(
+                !::bitfields::__private::any_overlap(&[
+                    #(::bitfields::__private::span::<_, #tys>(),)*
+                ]),
+                #message
+            )
+        };
+    }
+}
+
+fn expand_bit_enum(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let variants = match &input.data {
+        Data::Enum(data) => &data.variants,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                input,
+                "BitEnum can only be derived for enums",
+            ))
+        }
+    };
+    if let Some(variant) = variants.iter().find(|v| !matches!(v.fields, Fields::Unit)) {
+        return Err(syn::Error::new_spanned(
+            variant,
+            "BitEnum variants cannot have fields",
+        ));
+    }
+    if let Some((_, expr)) = variants
+        .iter()
+        .filter_map(|v| v.discriminant.as_ref())
+        .find(|(_, expr)| matches!(expr, Expr::Unary(unary) if matches!(unary.op, UnOp::Neg(_))))
+    {
+        return Err(syn::Error::new_spanned(
+            expr,
+            "BitEnum discriminants cannot be negative",
+        ));
+    }
+    let unsigned_repr = match int_repr(input)? {
+        Some(repr) if repr.to_string().starts_with('i') => {
+            return Err(syn::Error::new_spanned(
+                repr,
+                "BitEnum requires an unsigned repr",
+            ))
+        }
+        repr => repr.is_some(),
+    };
+    let idents: Vec<_> = variants.iter().map(|v| &v.ident).collect();
+    // Without a repr the discriminants are `isize`, and computed ones can still be negative.
+    // Such enums cannot be generic, since explicit discriminants on those need a repr.
+    let explicit = variants.iter().any(|v| v.discriminant.is_some());
+    let sign_check = if unsigned_repr || !explicit {
+        quote!()
+    } else {
+        quote! {
+            const _: () = {
+                #(
+                    ::core::assert!(
+                        #name::#idents as isize >= 0,
+                        "BitEnum discriminants cannot be negative"
+                    );
+                )*
+            };
+        }
+    };
+
+    Ok(quote! {
+        #sign_check
+
+        impl #impl_generics ::bitfields::BitEnum for #name #ty_generics #where_clause {
+            const MAX_DISCRIMINANT: u128 = {
+                let mut max = 0;
+                #(
+                    if #name::#idents as u128 > max {
+                        max = #name::#idents as u128;
+                    }
+                )*
+                max
+            };
+        }
+    })
+}
+
+/// The integer type named in the enum's `#[repr(...)]`, if any.
+fn int_repr(input: &DeriveInput) -> syn::Result<Option<syn::Ident>> {
+    const INTS: [&str; 12] = [
+        "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize",
+    ];
+    let mut found = None;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("repr"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if let Some(ident) = meta.path.get_ident() {
+                if INTS.contains(&ident.to_string().as_str()) {
+                    found = Some(ident.clone());
+                }
+            }
+            // Skip the arguments of `align(N)` and `packed(N)`
+            if meta.input.peek(syn::token::Paren) {
+                let args;
+                syn::parenthesized!(args in meta.input);
+                args.parse::<TokenStream2>()?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(found)
+}
+
+fn expand_from_bit_fields(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = struct_fields(input)?;
+    let check = layout_check(input, fields);
+    let get = quote!(::bitfields::BitFieldExt::get_as(storage)?);
+    let body = match fields {
+        Fields::Named(fields) => {
+            let members = fields.named.iter().map(|f| &f.ident);
+            quote!(#name { #(#members: #get,)* })
+        }
+        Fields::Unnamed(fields) => {
+            let members = fields.unnamed.iter().map(|_| &get);
+            quote!(#name(#(#members,)*))
+        }
+        Fields::Unit => quote!(#name),
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::bitfields::FromBitFields for #name #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn from_fields(
+                storage: &[u8],
+            ) -> ::core::result::Result<Self, ::bitfields::Error> {
+                #check
+                ::core::result::Result::Ok(#body)
+            }
+        }
+    })
+}
+
+fn expand_into_bit_fields(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = struct_fields(input)?;
+    let check = layout_check(input, fields);
+    let members: Vec<_> = match fields {
+        Fields::Named(fields) => fields
+            .named
+            .iter()
+            .map(|f| {
+                let ident = &f.ident;
+                quote!(self.#ident)
+            })
+            .collect(),
+        Fields::Unnamed(fields) => (0..fields.unnamed.len())
+            .map(|i| {
+                let index = Index::from(i);
+                quote!(self.#index)
+            })
+            .collect(),
+        Fields::Unit => Vec::new(),
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::bitfields::IntoBitFields for #name #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn into_fields(
+                self,
+                storage: &mut [u8],
+            ) -> ::core::result::Result<(), ::bitfields::Error> {
+                #check
+                #(::bitfields::BitFieldExt::store(storage, #members)?;)*
+                ::core::result::Result::Ok(())
+            }
+        }
+    })
+}
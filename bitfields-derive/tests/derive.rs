@@ -0,0 +1,224 @@
+use std::convert::TryFrom;
+
+use bitfields::{width_of, BitField, Error, StorageExt};
+use bitfields_derive::{BitEnum, FromBitFields, IntoBitFields};
+
+#[derive(Debug, PartialEq)]
+#[repr(u8)]
+enum Transport {
+    Tcp = 0b01,
+    Udp = 0b10,
+}
+
+impl BitField for Transport {
+    const POS: usize = 0;
+    const WIDTH: usize = 2;
+}
+
+impl From<Transport> for u8 {
+    fn from(field: Transport) -> u8 {
+        field as u8
+    }
+}
+
+impl TryFrom<u8> for Transport {
+    type Error = ();
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0b01 => Ok(Transport::Tcp),
+            0b10 => Ok(Transport::Udp),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Port(u8);
+
+impl BitField for Port {
+    const POS: usize = 8;
+    const WIDTH: usize = 8;
+}
+
+impl From<Port> for u8 {
+    fn from(field: Port) -> u8 {
+        field.0
+    }
+}
+
+impl TryFrom<u8> for Port {
+    type Error = ();
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(Port(value))
+    }
+}
+
+#[derive(Debug, PartialEq, FromBitFields, IntoBitFields)]
+struct Header {
+    transport: Transport,
+    port: Port,
+}
+
+#[derive(Debug, PartialEq, FromBitFields, IntoBitFields)]
+struct TupleHeader(Port, Transport);
+
+#[derive(Debug, PartialEq, FromBitFields, IntoBitFields)]
+struct Empty;
+
+#[test]
+fn named_struct_roundtrip() {
+    let mut storage = [0u8, 0u8];
+    storage
+        .encode(Header {
+            transport: Transport::Udp,
+            port: Port(80),
+        })
+        .unwrap();
+    assert_eq!(storage, [0b10, 80]);
+    assert_eq!(
+        storage.decode::<Header>().unwrap(),
+        Header {
+            transport: Transport::Udp,
+            port: Port(80),
+        }
+    );
+}
+
+#[test]
+fn tuple_struct_roundtrip() {
+    let mut storage = [0u8, 0u8];
+    storage
+        .encode(TupleHeader(Port(7), Transport::Tcp))
+        .unwrap();
+    assert_eq!(storage, [0b01, 7]);
+    assert_eq!(
+        storage.decode::<TupleHeader>().unwrap(),
+        TupleHeader(Port(7), Transport::Tcp)
+    );
+}
+
+#[test]
+fn unit_struct() {
+    let mut storage = [0xFFu8];
+    storage.encode(Empty).unwrap();
+    assert_eq!(storage, [0xFF]);
+    assert_eq!(storage.decode::<Empty>().unwrap(), Empty);
+}
+
+#[test]
+fn errors_are_propagated() {
+    assert!(matches!(
+        [0u8, 0u8].decode::<Header>(),
+        Err(Error::TryFromErr { raw: 0b00, .. })
+    ));
+    assert_eq!(
+        [0u8].encode(TupleHeader(Port(7), Transport::Tcp)),
+        Err(Error::InsufficientBytes)
+    );
+}
+
+/// Address whose width varies between hardware revisions
+#[derive(Debug, PartialEq)]
+struct Addr<const WIDTH: usize>(u8);
+
+impl<const WIDTH: usize> BitField for Addr<WIDTH> {
+    const POS: usize = 0;
+    const WIDTH: usize = WIDTH;
+}
+
+impl<const WIDTH: usize> From<Addr<WIDTH>> for u8 {
+    fn from(field: Addr<WIDTH>) -> u8 {
+        field.0
+    }
+}
+
+impl<const WIDTH: usize> TryFrom<u8> for Addr<WIDTH> {
+    type Error = ();
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(Addr(value))
+    }
+}
+
+/// Flags placed directly after an address of `OFFSET` bits
+#[derive(Debug, PartialEq)]
+struct Flags<const OFFSET: usize>(u8);
+
+impl<const OFFSET: usize> BitField for Flags<OFFSET> {
+    const POS: usize = OFFSET;
+    const WIDTH: usize = 2;
+}
+
+impl<const OFFSET: usize> From<Flags<OFFSET>> for u8 {
+    fn from(field: Flags<OFFSET>) -> u8 {
+        field.0
+    }
+}
+
+impl<const OFFSET: usize> TryFrom<u8> for Flags<OFFSET> {
+    type Error = ();
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(Flags(value))
+    }
+}
+
+#[derive(Debug, PartialEq, FromBitFields, IntoBitFields)]
+struct Register<const ADDR_WIDTH: usize> {
+    addr: Addr<ADDR_WIDTH>,
+    flags: Flags<ADDR_WIDTH>,
+}
+
+#[test]
+fn const_generic_widths() {
+    let mut storage = [0u8];
+    storage
+        .encode(Register::<4> {
+            addr: Addr(0b1010),
+            flags: Flags(0b11),
+        })
+        .unwrap();
+    assert_eq!(storage, [0b0011_1010]);
+
+    let mut storage = [0u8];
+    storage
+        .encode(Register::<6> {
+            addr: Addr(0b10_1010),
+            flags: Flags(0b11),
+        })
+        .unwrap();
+    assert_eq!(storage, [0b1110_1010]);
+    assert_eq!(
+        storage.decode::<Register<6>>().unwrap(),
+        Register {
+            addr: Addr(0b10_1010),
+            flags: Flags(0b11),
+        }
+    );
+
+    // The total is still checked against the storage when encoding
+    assert_eq!(
+        [0u8].encode(Register::<7> {
+            addr: Addr(0),
+            flags: Flags(0),
+        }),
+        Err(Error::InsufficientBytes)
+    );
+}
+
+#[derive(Debug, BitEnum)]
+#[allow(dead_code)]
+enum Mode {
+    Idle,
+    Run,
+    Fault = 5,
+    Halt,
+}
+
+#[test]
+fn bit_enum_width() {
+    assert_eq!(<Mode as bitfields::BitEnum>::MAX_DISCRIMINANT, 6);
+    assert_eq!(width_of::<Mode>(), 3);
+}
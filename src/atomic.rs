@@ -0,0 +1,175 @@
+//! Field access on atomic integers, for state words shared between threads.
+//!
+//! The word is read as its little-endian bytes, so a field at `POS` covers the same bits as in
+//! a byte storage holding that word. Writes go through compare-exchange loops that only
+//! change the field's bits, leaving concurrent writes to other fields intact.
+use core::convert::TryFrom;
+use core::sync::atomic::Ordering;
+
+use {read_bits, write_bits, BitField, BitFieldExt, BitStorage, Error};
+
+/// Typed field access on an atomic integer, see the [module docs](self).
+pub trait AtomicBitFieldExt<Repr: BitStorage = u8> {
+    fn load_field<Field>(&self, order: Ordering) -> Result<Field, Error>
+    where
+        Field: BitField<Repr> + TryFrom<Repr>;
+    /// Writes the field's bits with `order`, checking the value like
+    /// [BitFieldExt::store] does.
+    fn store_field<Field>(&self, field: Field, order: Ordering) -> Result<(), Error>
+    where
+        Field: BitField<Repr> + Into<Repr>;
+    /// Replaces the field with `f` applied to its current value and returns the previous value.
+    /// `f` may run several times if other threads change the word in between.
+    ///
+    /// The orderings are those of the integer's own `fetch_update`.
+    fn fetch_update_field<Field>(
+        &self,
+        set_order: Ordering,
+        fetch_order: Ordering,
+        f: impl FnMut(Field) -> Field,
+    ) -> Result<Field, Error>
+    where
+        Field: BitField<Repr> + TryFrom<Repr> + Into<Repr>;
+}
+
+/// The strongest failure ordering a compare-exchange with `order` on success allows.
+fn failure_order(order: Ordering) -> Ordering {
+    match order {
+        Ordering::Release => Ordering::Relaxed,
+        Ordering::AcqRel => Ordering::Acquire,
+        order => order,
+    }
+}
+
+macro_rules! impl_atomic_bit_field_ext {
+    ($($atomic:ident: $int:ty, $bits:literal);+ $(;)?) => {
+        $(
+            #[cfg(target_has_atomic = $bits)]
+            impl<Repr: BitStorage> AtomicBitFieldExt<Repr> for core::sync::atomic::$atomic {
+                fn load_field<Field>(&self, order: Ordering) -> Result<Field, Error>
+                where
+                    Field: BitField<Repr> + TryFrom<Repr>,
+                {
+                    self.load(order).to_le_bytes()[..].get_as::<Field>()
+                }
+
+                fn store_field<Field>(&self, field: Field, order: Ordering) -> Result<(), Error>
+                where
+                    Field: BitField<Repr> + Into<Repr>,
+                {
+                    // Check the value once up front, so that retries cannot fail
+                    let mut scratch = (0 as $int).to_le_bytes();
+                    scratch[..].store(field)?;
+                    let bits = read_bits(&scratch, Field::POS, Field::WIDTH);
+
+                    let _ = self.fetch_update(order, failure_order(order), |word| {
+                        let mut bytes = word.to_le_bytes();
+                        write_bits(&mut bytes, Field::POS, Field::WIDTH, bits);
+                        Some(<$int>::from_le_bytes(bytes))
+                    });
+                    Ok(())
+                }
+
+                fn fetch_update_field<Field>(
+                    &self,
+                    set_order: Ordering,
+                    fetch_order: Ordering,
+                    mut f: impl FnMut(Field) -> Field,
+                ) -> Result<Field, Error>
+                where
+                    Field: BitField<Repr> + TryFrom<Repr> + Into<Repr>,
+                {
+                    let mut error = None;
+                    let previous = self.fetch_update(set_order, fetch_order, |word| {
+                        let mut bytes = word.to_le_bytes();
+                        let updated = bytes[..]
+                            .get_as::<Field>()
+                            .and_then(|field| bytes[..].store(f(field)));
+                        match updated {
+                            Ok(()) => Some(<$int>::from_le_bytes(bytes)),
+                            Err(err) => {
+                                error = Some(err);
+                                None
+                            }
+                        }
+                    });
+                    match (previous, error) {
+                        (Ok(word), _) => word.to_le_bytes()[..].get_as::<Field>(),
+                        (Err(_), Some(err)) => Err(err),
+                        (Err(_), None) => unreachable!("fetch_update only fails when the closure does"),
+                    }
+                }
+            }
+        )+
+    };
+}
+
+impl_atomic_bit_field_ext!(
+    AtomicU8: u8, "8";
+    AtomicU16: u16, "16";
+    AtomicU32: u32, "32";
+    AtomicU64: u64, "64";
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixtures::*;
+
+    #[test]
+    fn atomic_fields() {
+        use core::sync::atomic::{AtomicU32, Ordering};
+
+        let word = AtomicU32::new(0);
+        word.store_field(Transport::UDP, Ordering::Relaxed).unwrap();
+        word.store_field(Length(0xABC), Ordering::Relaxed).unwrap();
+        assert_eq!(word.load(Ordering::Relaxed), 0xABC2);
+        assert_eq!(
+            word.load_field::<Transport>(Ordering::Relaxed),
+            Ok(Transport::UDP)
+        );
+        assert_eq!(
+            word.store_field(WideWithOffset::A, Ordering::Relaxed),
+            Err(Error::ValueOutOfRange)
+        );
+        assert_eq!(word.load(Ordering::Relaxed), 0xABC2);
+
+        // Concurrent updates to different fields of the word do not clobber each other
+        let word = AtomicU32::new(0);
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                for _ in 0..1000 {
+                    word.fetch_update_field::<Length>(Ordering::AcqRel, Ordering::Acquire, |l| {
+                        Length(l.0 + 1)
+                    })
+                    .unwrap();
+                }
+            });
+            scope.spawn(|| {
+                for i in 0..1000 {
+                    let transport = if i % 2 == 0 {
+                        Transport::TCP
+                    } else {
+                        Transport::UDT
+                    };
+                    word.store_field(transport, Ordering::Release).unwrap();
+                }
+            });
+        });
+        assert_eq!(
+            word.load_field::<Length>(Ordering::Acquire),
+            Ok(Length(1000))
+        );
+        assert_eq!(
+            word.load_field::<Transport>(Ordering::Acquire),
+            Ok(Transport::UDT)
+        );
+
+        // A decode failure stops the update
+        let word = AtomicU32::new(0);
+        assert!(matches!(
+            word.fetch_update_field::<Transport>(Ordering::Relaxed, Ordering::Relaxed, |t| t),
+            Err(Error::TryFromErr { .. })
+        ));
+    }
+}
@@ -0,0 +1,121 @@
+//! Arrays of identical registers sharing one layout, such as 16 channel-config registers laid
+//! out back to back in a byte region.
+use core::ops::{Index, IndexMut};
+
+/// `len()` registers of `register_len` bytes each, borrowed from one contiguous region.
+///
+/// Each register is a plain `[u8]` storage, so it is read and written through
+/// [BitFieldExt](::BitFieldExt) as usual: `bank[3].store(Gain::High)`. Since the bank
+/// borrows the region, writes land in the region directly.
+#[derive(Debug)]
+pub struct Bank<'a> {
+    region: &'a mut [u8],
+    register_len: usize,
+}
+
+impl<'a> Bank<'a> {
+    /// Splits `region` into registers of `register_len` bytes. Returns `None` if `register_len`
+    /// is 0 or does not evenly divide the region.
+    pub fn new(region: &'a mut [u8], register_len: usize) -> Option<Bank<'a>> {
+        if register_len == 0 || !region.len().is_multiple_of(register_len) {
+            return None;
+        }
+        Some(Bank {
+            region,
+            register_len,
+        })
+    }
+
+    /// Number of registers in the bank.
+    pub fn len(&self) -> usize {
+        self.region.len() / self.register_len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.region.is_empty()
+    }
+
+    pub fn register_len(&self) -> usize {
+        self.register_len
+    }
+
+    pub fn get(&self, idx: usize) -> Option<&[u8]> {
+        self.region.chunks_exact(self.register_len).nth(idx)
+    }
+
+    pub fn get_mut(&mut self, idx: usize) -> Option<&mut [u8]> {
+        self.region.chunks_exact_mut(self.register_len).nth(idx)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &[u8]> {
+        self.region.chunks_exact(self.register_len)
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut [u8]> {
+        self.region.chunks_exact_mut(self.register_len)
+    }
+}
+
+impl<'a> Index<usize> for Bank<'a> {
+    type Output = [u8];
+
+    /// # Panics
+    /// Panics if `idx` is out of bounds.
+    fn index(&self, idx: usize) -> &[u8] {
+        self.get(idx).expect("register index out of bounds")
+    }
+}
+
+impl<'a> IndexMut<usize> for Bank<'a> {
+    /// # Panics
+    /// Panics if `idx` is out of bounds.
+    fn index_mut(&mut self, idx: usize) -> &mut [u8] {
+        self.get_mut(idx).expect("register index out of bounds")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+    use fixtures::*;
+    use BitFieldExt;
+
+    #[test]
+    fn register_bank() {
+        let mut region = [0u8; 8];
+        {
+            let mut bank = Bank::new(&mut region, 2).unwrap();
+            assert_eq!(bank.len(), 4);
+            bank[3].store(SecondByte(0xAB)).unwrap();
+            bank[1].store(Transport::UDP).unwrap();
+            bank.get_mut(0).unwrap().store(AddressType::IPv6).unwrap();
+            assert!(bank.get_mut(4).is_none());
+
+            assert_eq!(bank[3].get_as::<SecondByte>().unwrap(), SecondByte(0xAB));
+            assert_eq!(
+                bank.iter()
+                    .map(|reg| reg.get_as::<SecondByte>().unwrap().0)
+                    .collect::<Vec<_>>(),
+                vec![0, 0, 0, 0xAB]
+            );
+            for register in bank.iter_mut() {
+                register.store(Transport::TCP).unwrap();
+            }
+        }
+        assert_eq!(region, [0b0101, 0, 0b01, 0, 0b01, 0, 0b01, 0xAB]);
+
+        assert!(Bank::new(&mut region, 3).is_none());
+        assert!(Bank::new(&mut region, 0).is_none());
+        assert!(Bank::new(&mut [], 4).unwrap().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "register index out of bounds")]
+    fn register_index_does_not_wrap() {
+        let mut region = [0u8; 8];
+        let bank = Bank::new(&mut region, 2).unwrap();
+        // The byte offset of this index wraps around to 0 when computed unchecked
+        let _ = &bank[usize::MAX / 2 + 1];
+    }
+}
@@ -0,0 +1,77 @@
+//! Helpers for negotiating feature-flag words, where each capability is one or more bits that
+//! a peer sets when it supports the feature. See [wide fields](crate#wide-fields).
+use alloc::vec::Vec;
+use bit_twiddles::{byte_bit_offset, test_bit, BITS_PER_BYTE};
+
+use {check_span, read_repr, BitField, Error};
+
+pub trait Capabilities {
+    /// Whether every bit of the capability `Cap` is set.
+    fn supports<Cap>(&self) -> Result<bool, Error>
+    where
+        Cap: BitField<u8>;
+    /// The capabilities set in both `self` and `other`. Bits past the end of the shorter
+    /// storage are treated as unset, so the result has the length of the shorter one.
+    fn intersection(&self, other: &[u8]) -> Vec<u8>;
+    /// Indices of the bits set in `required` but not in `self`, in ascending order. Bits past
+    /// the end of `self` count as missing.
+    fn missing_required(&self, required: &[u8]) -> Vec<usize>;
+}
+
+impl Capabilities for [u8] {
+    fn supports<Cap>(&self) -> Result<bool, Error>
+    where
+        Cap: BitField<u8>,
+    {
+        check_span(self, Cap::POS, Cap::WIDTH)?;
+        let all_set = ((1_u16 << Cap::WIDTH) - 1) as u8;
+        Ok(read_repr(self, Cap::POS, Cap::WIDTH) == all_set)
+    }
+
+    fn intersection(&self, other: &[u8]) -> Vec<u8> {
+        self.iter().zip(other).map(|(a, b)| a & b).collect()
+    }
+
+    fn missing_required(&self, required: &[u8]) -> Vec<usize> {
+        (0..BITS_PER_BYTE * required.len())
+            .filter(|&idx| {
+                let (byte, bit) = byte_bit_offset(idx);
+                let supported = self.get(byte).is_some_and(|b| test_bit(b, bit));
+                test_bit(&required[byte], bit) && !supported
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capability_negotiation() {
+        /// Two-bit capability, only supported when both bits are set
+        #[derive(Debug)]
+        struct Compression;
+
+        impl BitField for Compression {
+            const POS: usize = 8;
+            const WIDTH: usize = 2;
+        }
+
+        let local = [0b0000_1111u8, 0b0000_0011];
+        let peer = [0b0000_0101u8, 0b0000_0001];
+        assert_eq!(local.supports::<Compression>(), Ok(true));
+        assert_eq!(peer.supports::<Compression>(), Ok(false));
+        assert_eq!(
+            local[..1].supports::<Compression>(),
+            Err(Error::InsufficientBytes)
+        );
+
+        assert_eq!(local.intersection(&peer), vec![0b0000_0101, 0b0000_0001]);
+        assert_eq!(local.intersection(&peer[..1]), vec![0b0000_0101]);
+
+        assert_eq!(peer.missing_required(&local), vec![1, 3, 9]);
+        assert!(local.missing_required(&peer).is_empty());
+        assert_eq!(local[..1].missing_required(&local), vec![8, 9]);
+    }
+}
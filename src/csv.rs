@@ -0,0 +1,135 @@
+//! CSV/TSV export of decoded storages, one row per storage and one column per field.
+//!
+//! Columns hold the raw field bits as unsigned integers, so that representations the field
+//! type would reject are still exported as-is.
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+#[cfg(feature = "std")]
+use std::io::{self, Write};
+
+use {Error, FieldSpans};
+
+/// Formats the raw values of `Fields` in `storage` as a comma-separated row, without the
+/// trailing newline.
+pub fn to_csv_row<Fields: FieldSpans>(storage: &[u8]) -> Result<String, Error> {
+    CsvExporter::<Fields>::new().row(storage)
+}
+
+/// Writes rows of raw field values for a [FieldSpans] describing the columns.
+#[derive(Debug)]
+pub struct CsvExporter<Fields> {
+    delimiter: char,
+    fields: PhantomData<Fields>,
+}
+
+impl<Fields: FieldSpans> CsvExporter<Fields> {
+    /// A comma-separated exporter.
+    pub fn new() -> Self {
+        CsvExporter {
+            delimiter: ',',
+            fields: PhantomData,
+        }
+    }
+
+    /// A tab-separated exporter.
+    pub fn tsv() -> Self {
+        Self::new().with_delimiter('\t')
+    }
+
+    pub fn with_delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// The header row, holding the field type names.
+    pub fn header(&self) -> String {
+        let names: Vec<String> = Fields::spans()
+            .iter()
+            .map(|span| self.escape(span.name))
+            .collect();
+        names.join(&self.delimiter.to_string())
+    }
+
+    /// A single row of raw field values read from `storage`.
+    pub fn row(&self, storage: &[u8]) -> Result<String, Error> {
+        let values = Fields::spans()
+            .iter()
+            .map(|span| span.read(storage).map(|raw| raw.to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(values.join(&self.delimiter.to_string()))
+    }
+
+    /// Writes the header followed by one row per record.
+    ///
+    /// A record that does not fit the fields is reported as an [io::ErrorKind::InvalidData]
+    /// error, after the rows preceding it have been written. Requires the `std` feature.
+    #[cfg(feature = "std")]
+    pub fn export<W, I, R>(&self, writer: &mut W, records: I) -> io::Result<()>
+    where
+        W: Write,
+        I: IntoIterator<Item = R>,
+        R: AsRef<[u8]>,
+    {
+        writeln!(writer, "{}", self.header())?;
+        for record in records {
+            let row = self
+                .row(record.as_ref())
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", err)))?;
+            writeln!(writer, "{}", row)?;
+        }
+        Ok(())
+    }
+
+    fn escape(&self, cell: &str) -> String {
+        if cell.contains(self.delimiter) || cell.contains('"') || cell.contains('\n') {
+            format!("\"{}\"", cell.replace('"', "\"\""))
+        } else {
+            cell.to_string()
+        }
+    }
+}
+
+impl<Fields: FieldSpans> Default for CsvExporter<Fields> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use fixtures::*;
+
+    #[test]
+    fn csv_export() {
+        type Columns = (Transport, AddressType, SecondByte);
+
+        assert_eq!(to_csv_row::<Columns>(&[0b0000_1001, 7]).unwrap(), "1,2,7");
+        // Invalid representations are exported raw
+        assert_eq!(to_csv_row::<Columns>(&[0b0000_1100, 0]).unwrap(), "0,3,0");
+        assert_eq!(
+            to_csv_row::<Columns>(&[0b0000_1001]),
+            Err(Error::InsufficientBytes)
+        );
+
+        let mut out = Vec::new();
+        CsvExporter::<Columns>::tsv()
+            .export(&mut out, vec![[0b01u8, 1], [0b0111, 255]])
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Transport\tAddressType\tSecondByte\n1\t0\t1\n3\t1\t255\n"
+        );
+
+        let mut out = Vec::new();
+        let err = CsvExporter::<Columns>::new()
+            .export(&mut out, vec![vec![0b01u8, 1], vec![0b01]])
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Transport,AddressType,SecondByte\n1,0,1\n"
+        );
+    }
+}
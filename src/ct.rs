@@ -0,0 +1,73 @@
+//! Field access for secret-dependent values, such as flags packed into cryptographic protocol
+//! state.
+//!
+//! [BitFieldExt::store](::BitFieldExt::store) and [BitFieldExt::get_as](::BitFieldExt::get_as)
+//! branch on the value itself: range checks, validation hooks and `TryFrom` impls all do.
+//! The functions here work on raw representations instead, and only branch on the field's
+//! `POS` and `WIDTH` and on the storage length, which are public. Bits are moved with shifts
+//! and masks, and conditional writes use mask-based selection.
+//!
+//! This is best effort: the code avoids secret-dependent branches and lookups, but Rust gives
+//! no guarantee that the optimizer will not introduce some. See
+//! [wide fields](crate#wide-fields).
+use core::hint::black_box;
+
+use {check_span, read_repr, write_repr, BitField, Error};
+
+/// Reads the raw bits of `Field`.
+pub fn load<Field: BitField<u8>>(storage: &[u8]) -> Result<u8, Error> {
+    check_span(storage, Field::POS, Field::WIDTH)?;
+    Ok(read_repr(storage, Field::POS, Field::WIDTH))
+}
+
+/// Writes the low `Field::WIDTH` bits of `repr`. Higher bits are masked off rather than
+/// rejected, and [BitField::validate] is not run, since either would depend on the value.
+pub fn store<Field: BitField<u8>>(storage: &mut [u8], repr: u8) -> Result<(), Error> {
+    check_span(storage, Field::POS, Field::WIDTH)?;
+    write_repr(storage, Field::POS, Field::WIDTH, repr);
+    Ok(())
+}
+
+/// Writes `repr` like [store] if `choice` is 1, and rewrites the current bits unchanged if it
+/// is 0, doing the same work either way.
+pub fn conditional_store<Field: BitField<u8>>(
+    storage: &mut [u8],
+    repr: u8,
+    choice: u8,
+) -> Result<(), Error> {
+    let current = load::<Field>(storage)?;
+    store::<Field>(storage, select(choice, repr, current))
+}
+
+/// Returns `a` if the low bit of `choice` is 1 and `b` if it is 0, without branching.
+pub fn select(choice: u8, a: u8, b: u8) -> u8 {
+    let mask = black_box(0_u8.wrapping_sub(choice & 1));
+    (a & mask) | (b & !mask)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixtures::*;
+
+    #[test]
+    fn constant_time_access() {
+        let mut storage = [0b1111_0000u8];
+        store::<AddressType>(&mut storage, 0b10).unwrap();
+        assert_eq!(storage, [0b1111_1000]);
+        assert_eq!(load::<AddressType>(&storage), Ok(0b10));
+
+        // Out-of-range bits are masked off instead of rejected
+        store::<Transport>(&mut storage, 0b1111_1101).unwrap();
+        assert_eq!(storage, [0b1111_1001]);
+
+        conditional_store::<Transport>(&mut storage, 0b10, 0).unwrap();
+        assert_eq!(storage, [0b1111_1001]);
+        conditional_store::<Transport>(&mut storage, 0b10, 1).unwrap();
+        assert_eq!(storage, [0b1111_1010]);
+
+        assert_eq!(select(1, 0xAA, 0x55), 0xAA);
+        assert_eq!(select(0, 0xAA, 0x55), 0x55);
+        assert_eq!(load::<SecondByte>(&storage), Err(Error::InsufficientBytes));
+    }
+}
@@ -0,0 +1,70 @@
+//! XOR deltas between successive storages, so that a stream only needs to carry the bits that
+//! changed since the previous record.
+use alloc::vec::Vec;
+
+use {Error, FieldSpan, FieldSpans};
+
+pub trait Delta {
+    /// The bits that differ between `prev` and `self`, as `prev ^ self`.
+    fn delta(&self, prev: &[u8]) -> Result<Vec<u8>, Error>;
+    /// Flips the bits set in `delta`, turning the previous storage into the current one.
+    fn apply_delta(&mut self, delta: &[u8]) -> Result<(), Error>;
+}
+
+impl Delta for [u8] {
+    fn delta(&self, prev: &[u8]) -> Result<Vec<u8>, Error> {
+        if self.len() != prev.len() {
+            return Err(Error::WidthMismatch);
+        }
+        Ok(self
+            .iter()
+            .zip(prev)
+            .map(|(cur, prev)| cur ^ prev)
+            .collect())
+    }
+
+    fn apply_delta(&mut self, delta: &[u8]) -> Result<(), Error> {
+        if self.len() != delta.len() {
+            return Err(Error::WidthMismatch);
+        }
+        self.iter_mut()
+            .zip(delta)
+            .for_each(|(byte, delta)| *byte ^= delta);
+        Ok(())
+    }
+}
+
+/// The fields of `Fields` that `delta` changes, in tuple order.
+pub fn touched_fields<Fields: FieldSpans>(delta: &[u8]) -> Result<Vec<FieldSpan>, Error> {
+    let mut touched = Vec::new();
+    for span in Fields::spans() {
+        if span.read(delta)? != 0 {
+            touched.push(span);
+        }
+    }
+    Ok(touched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixtures::*;
+
+    #[test]
+    fn xor_delta() {
+        let prev = [0b0000_0101u8, 0x10];
+        let cur = [0b0000_1001u8, 0x10];
+        let delta = cur.delta(&prev).unwrap();
+        assert_eq!(delta, vec![0b0000_1100, 0x00]);
+
+        let touched = touched_fields::<(Transport, AddressType, SecondByte)>(&delta).unwrap();
+        assert_eq!(touched, vec![FieldSpan::of::<AddressType>()]);
+
+        let mut state = prev;
+        state.apply_delta(&delta).unwrap();
+        assert_eq!(state, cur);
+
+        assert_eq!(cur.delta(&prev[..1]), Err(Error::WidthMismatch));
+        assert_eq!(state.apply_delta(&[0u8]), Err(Error::WidthMismatch));
+    }
+}
@@ -0,0 +1,152 @@
+//! Field-by-field comparison and merging of two storages under the same [FieldSpans].
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use {write_repr, Error, FieldSpan, FieldSpans};
+
+/// A field whose raw bits differ between two storages.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FieldDiff {
+    pub span: FieldSpan,
+    pub old: u8,
+    pub new: u8,
+}
+
+impl FieldDiff {
+    /// Bits of the field that differ, relative to the field's lowest bit.
+    pub fn changed_bits(&self) -> u8 {
+        self.old ^ self.new
+    }
+}
+
+/// Renders as `AddressType [3:2]: 01 -> 10 (^^)`, marking changed bits with `^` and unchanged
+/// ones with `.`, most significant bit first.
+impl fmt::Display for FieldDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = self.span.width;
+        let markers: String = (0..width)
+            .rev()
+            .map(|i| {
+                if (self.changed_bits() >> i) & 1 == 1 {
+                    '^'
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        write!(
+            f,
+            "{} [{}:{}]: {:0w$b} -> {:0w$b} ({})",
+            self.span.name,
+            self.span.pos + width - 1,
+            self.span.pos,
+            self.old,
+            self.new,
+            markers,
+            w = width
+        )
+    }
+}
+
+/// Compares the raw bits of every field in `Fields` between `old` and `new`, returning the
+/// fields that changed in tuple order.
+pub fn diff_fields<Fields: FieldSpans>(old: &[u8], new: &[u8]) -> Result<Vec<FieldDiff>, Error> {
+    let mut diffs = Vec::new();
+    for span in Fields::spans() {
+        let (old, new) = (span.read(old)?, span.read(new)?);
+        if old != new {
+            diffs.push(FieldDiff { span, old, new });
+        }
+    }
+    Ok(diffs)
+}
+
+/// Whether `a` and `b` hold the same bits in every field of `Fields`, ignoring unclaimed bits.
+pub fn fields_eq<Fields: FieldSpans>(a: &[u8], b: &[u8]) -> Result<bool, Error> {
+    Ok(diff_fields::<Fields>(a, b)?.is_empty())
+}
+
+/// Copies the fields of `Fields` that are set in `src` but zero in `dst` over to `dst`.
+///
+/// Fails with [Error::FieldConflict] if a field is non-zero in both with different values, in
+/// which case `dst` is left unchanged. Unclaimed bits of `dst` are never touched. Copied values
+/// are raw bits and do not go through [BitField::validate](::BitField::validate).
+pub fn merge_fields<Fields: FieldSpans>(dst: &mut [u8], src: &[u8]) -> Result<(), Error> {
+    let mut writes = Vec::new();
+    for diff in diff_fields::<Fields>(dst, src)? {
+        match (diff.old, diff.new) {
+            (_, 0) => continue,
+            (0, new) => writes.push((diff.span, new)),
+            _ => return Err(Error::FieldConflict(diff.span.name)),
+        }
+    }
+    for (span, repr) in writes {
+        write_repr(dst, span.pos, span.width, repr);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use fixtures::*;
+
+    #[test]
+    fn diff_changed_fields() {
+        type Fields = (Transport, AddressType, SecondByte);
+
+        let old = [0b0000_0101u8, 0x0F];
+        let new = [0b0000_1001u8, 0x0F];
+        let diffs = diff_fields::<Fields>(&old, &new).unwrap();
+        assert_eq!(
+            diffs,
+            vec![FieldDiff {
+                span: FieldSpan::of::<AddressType>(),
+                old: 0b01,
+                new: 0b10,
+            }]
+        );
+        assert_eq!(diffs[0].changed_bits(), 0b11);
+        assert_eq!(diffs[0].to_string(), "AddressType [3:2]: 01 -> 10 (^^)");
+
+        let new = [0b0000_0101u8, 0x1F];
+        let diffs = diff_fields::<Fields>(&old, &new).unwrap();
+        assert_eq!(
+            diffs[0].to_string(),
+            "SecondByte [15:8]: 00001111 -> 00011111 (...^....)"
+        );
+
+        assert_eq!(diff_fields::<Fields>(&old, &old), Ok(vec![]));
+        assert_eq!(
+            diff_fields::<Fields>(&old, &[0u8]),
+            Err(Error::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn merge_non_conflicting_fields() {
+        type Fields = (Transport, AddressType, SecondByte);
+        assert_eq!(
+            fields_eq::<Fields>(&[0b1111_0001, 7], &[0b0000_0001, 7]),
+            Ok(true)
+        );
+        assert_eq!(fields_eq::<Fields>(&[0b01, 7], &[0b01, 8]), Ok(false));
+        assert_eq!(
+            fields_eq::<Fields>(&[0b01], &[0b01, 7]),
+            Err(Error::InsufficientBytes)
+        );
+
+        let mut dst = [0b1000_0001u8, 0];
+        merge_fields::<Fields>(&mut dst, &[0b0000_1001, 42]).unwrap();
+        assert_eq!(dst, [0b1000_1001, 42]);
+
+        let mut dst = [0b0000_0001u8, 0];
+        assert_eq!(
+            merge_fields::<Fields>(&mut dst, &[0b0000_1010, 42]),
+            Err(Error::FieldConflict("Transport"))
+        );
+        assert_eq!(dst, [0b0000_0001, 0]);
+    }
+}
@@ -0,0 +1,85 @@
+//! Human-readable renderings of a storage under a [FieldSpans], for logs and debugging.
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use {Error, FieldSpan, FieldSpans, StorageExt};
+
+/// Renders the fields of `Fields`, highest bit first, as e.g.
+/// `[7] Flag=1 (1) | [6:2] Protocol=00010 (2) | [1:0] Transport=01 (1)`, showing each field's
+/// bit range, binary value and integer value.
+pub fn dump_fields<Fields: FieldSpans>(storage: &[u8]) -> Result<String, Error> {
+    let fields = storage
+        .field_values::<Fields>()?
+        .into_iter()
+        .rev()
+        .map(|(span, raw)| {
+            format!(
+                "{} {}={:0w$b} ({})",
+                bit_range(&span),
+                span.name,
+                raw,
+                raw,
+                w = span.width
+            )
+        })
+        .collect::<Vec<_>>();
+    Ok(fields.join(" | "))
+}
+
+/// Renders every bit of `storage`, highest bit first with a space between bytes, above a line
+/// marking with `^` the bits that no field of `Fields` claims:
+///
+/// ```text
+/// 00001001
+/// ^^^^
+/// ```
+pub fn dump_binary<Fields: FieldSpans>(storage: &[u8]) -> String {
+    let spans = Fields::spans();
+    let (mut bits, mut markers) = (String::new(), String::new());
+    for byte in (0..storage.len()).rev() {
+        if byte + 1 != storage.len() {
+            bits.push(' ');
+            markers.push(' ');
+        }
+        for bit in (0..8).rev() {
+            bits.push(if (storage[byte] >> bit) & 1 == 1 {
+                '1'
+            } else {
+                '0'
+            });
+            let claimed = spans.iter().any(|span| span.contains(8 * byte + bit));
+            markers.push(if claimed { ' ' } else { '^' });
+        }
+    }
+    format!("{}\n{}", bits, markers.trim_end())
+}
+
+fn bit_range(span: &FieldSpan) -> String {
+    if span.width <= 1 {
+        format!("[{}]", span.pos)
+    } else {
+        format!("[{}:{}]", span.pos + span.width - 1, span.pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixtures::*;
+
+    #[test]
+    fn dump() {
+        type Fields = (Transport, AddressType, SecondByte);
+        let storage = [0b0000_1001u8, 42];
+        assert_eq!(
+            dump_fields::<Fields>(&storage).unwrap(),
+            "[15:8] SecondByte=00101010 (42) | [3:2] AddressType=10 (2) | [1:0] Transport=01 (1)"
+        );
+        assert_eq!(dump_fields::<Fields>(&[0u8]), Err(Error::InsufficientBytes));
+
+        assert_eq!(
+            dump_binary::<(Transport, AddressType)>(&storage),
+            "00101010 00001001\n^^^^^^^^ ^^^^"
+        );
+    }
+}
@@ -0,0 +1,286 @@
+//! Field types shared by the tests of several modules.
+use alloc::string::String;
+use core::convert::TryFrom;
+
+use {BitEnum, BitField, BitFieldExt, Error, FromBitFields, IntoBitFields};
+
+#[derive(Clone, Debug, PartialEq)]
+#[repr(u8)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum Transport {
+    TCP = 0b01,
+    UDP = 0b10,
+    UDT = 0b11,
+}
+
+impl BitField for Transport {
+    const POS: usize = 0;
+    const WIDTH: usize = 2;
+}
+
+impl From<Transport> for u8 {
+    fn from(field: Transport) -> u8 {
+        field as u8
+    }
+}
+
+impl TryFrom<u8> for Transport {
+    type Error = ();
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0b01 => Ok(Transport::TCP),
+            0b10 => Ok(Transport::UDP),
+            0b11 => Ok(Transport::UDT),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[repr(u8)]
+pub enum WideWithOffset {
+    A = 0b1111_1000,
+    B = 0b1111_1100,
+    C = 0b1111_1110,
+    D = 0b1111_1111,
+}
+
+impl BitField for WideWithOffset {
+    const POS: usize = 7;
+    const WIDTH: usize = 2;
+}
+
+impl From<WideWithOffset> for u8 {
+    fn from(field: WideWithOffset) -> u8 {
+        field as u8
+    }
+}
+
+impl TryFrom<u8> for WideWithOffset {
+    type Error = ();
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0b1111_1000 => Ok(WideWithOffset::A),
+            0b1111_1100 => Ok(WideWithOffset::B),
+            0b1111_1110 => Ok(WideWithOffset::C),
+            0b1111_1111 => Ok(WideWithOffset::D),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+#[repr(u8)]
+pub enum InvalidWidth {
+    DoNotCare = 0b0,
+}
+
+impl BitField for InvalidWidth {
+    const POS: usize = 0;
+    const WIDTH: usize = 9; // exceeds current allowed width
+}
+
+impl From<InvalidWidth> for u8 {
+    fn from(field: InvalidWidth) -> u8 {
+        field as u8
+    }
+}
+
+impl TryFrom<u8> for InvalidWidth {
+    type Error = ();
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0b0 => Ok(InvalidWidth::DoNotCare),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[repr(u8)]
+pub enum AddressType {
+    IPv4 = 0,
+    IPv6 = 1,
+    DomainName = 2,
+}
+
+impl BitField for AddressType {
+    const POS: usize = 2;
+    const WIDTH: usize = 2;
+}
+
+impl From<AddressType> for u8 {
+    fn from(field: AddressType) -> u8 {
+        field as u8
+    }
+}
+
+impl TryFrom<u8> for AddressType {
+    type Error = ();
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(AddressType::IPv4),
+            1 => Ok(AddressType::IPv6),
+            2 => Ok(AddressType::DomainName),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Shares bit 1 with [Transport] and bit 2 with [AddressType]
+#[derive(Debug)]
+pub struct Straddling;
+
+impl BitField for Straddling {
+    const POS: usize = 1;
+    const WIDTH: usize = 2;
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SecondByte(pub u8);
+
+impl BitField for SecondByte {
+    const POS: usize = 8;
+    const WIDTH: usize = 8;
+}
+
+impl From<SecondByte> for u8 {
+    fn from(field: SecondByte) -> u8 {
+        field.0
+    }
+}
+
+impl TryFrom<u8> for SecondByte {
+    type Error = ();
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(SecondByte(value))
+    }
+}
+
+/// A 12-bit length straddling three bytes, too wide for a `u8` representation
+#[derive(Debug, PartialEq)]
+pub struct Length(pub u16);
+
+impl BitField<u16> for Length {
+    const POS: usize = 4;
+    const WIDTH: usize = 12;
+}
+
+impl From<Length> for u16 {
+    fn from(field: Length) -> u16 {
+        field.0
+    }
+}
+
+impl TryFrom<u16> for Length {
+    type Error = ();
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        Ok(Length(value))
+    }
+}
+
+/// A full 64-bit register that starts mid-byte
+#[derive(Debug, PartialEq)]
+pub struct Counter(pub u64);
+
+impl BitField<u64> for Counter {
+    const POS: usize = 3;
+    const WIDTH: usize = 64;
+}
+
+impl From<Counter> for u64 {
+    fn from(field: Counter) -> u64 {
+        field.0
+    }
+}
+
+impl TryFrom<u64> for Counter {
+    type Error = ();
+
+    fn try_from(value: u64) -> Result<Self, Self::Error> {
+        Ok(Counter(value))
+    }
+}
+
+/// Burst length in beats, which the hardware requires to be a power of two
+#[derive(Debug, PartialEq)]
+pub struct BurstLen(pub u8);
+
+impl BitField for BurstLen {
+    const POS: usize = 4;
+    const WIDTH: usize = 4;
+
+    fn validate(repr: u8) -> Result<(), String> {
+        if repr.is_power_of_two() {
+            Ok(())
+        } else {
+            Err(format!("burst length {} is not a power of two", repr))
+        }
+    }
+}
+
+impl From<BurstLen> for u8 {
+    fn from(field: BurstLen) -> u8 {
+        field.0
+    }
+}
+
+#[derive(Debug)]
+pub struct ThreeWide;
+
+impl BitField for ThreeWide {
+    const POS: usize = 4;
+    const WIDTH: usize = 3;
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ActorPathHeader {
+    pub transport: Transport,
+    pub address: AddressType,
+}
+
+impl FromBitFields for ActorPathHeader {
+    fn from_fields(storage: &[u8]) -> Result<Self, Error> {
+        Ok(ActorPathHeader {
+            transport: storage.get_as()?,
+            address: storage.get_as()?,
+        })
+    }
+}
+
+impl IntoBitFields for ActorPathHeader {
+    fn into_fields(self, storage: &mut [u8]) -> Result<(), Error> {
+        storage.store(self.transport)?;
+        storage.store(self.address)
+    }
+}
+
+#[derive(Debug)]
+pub struct Ack;
+
+impl BitField for Ack {
+    const POS: usize = 5;
+    const WIDTH: usize = 1;
+}
+
+#[derive(Debug)]
+pub struct Fin;
+
+impl BitField for Fin {
+    const POS: usize = 6;
+    const WIDTH: usize = 1;
+}
+
+impl BitEnum for Transport {
+    const MAX_DISCRIMINANT: u128 = Transport::UDT as u128;
+}
+
+impl BitEnum for AddressType {
+    const MAX_DISCRIMINANT: u128 = AddressType::DomainName as u128;
+}
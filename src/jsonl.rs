@@ -0,0 +1,88 @@
+//! JSON Lines export of decoded storages, one object per storage keyed by field name, for
+//! feeding captures into tools such as `jq`.
+//!
+//! As with [csv](::csv), values are the raw field bits as unsigned integers.
+use alloc::string::String;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::{self, Write};
+
+use {Error, FieldSpans};
+
+/// Formats the raw values of `Fields` in `storage` as a single-line JSON object, e.g.
+/// `{"Transport":1,"AddressType":2}`.
+pub fn to_json_object<Fields: FieldSpans>(storage: &[u8]) -> Result<String, Error> {
+    let members = Fields::spans()
+        .iter()
+        .map(|span| Ok(format!("{}:{}", quote(span.name), span.read(storage)?)))
+        .collect::<Result<Vec<_>, Error>>()?;
+    Ok(format!("{{{}}}", members.join(",")))
+}
+
+/// Writes one JSON object per record, each followed by a newline.
+///
+/// A record that does not fit the fields is reported as an [io::ErrorKind::InvalidData]
+/// error, after the records preceding it have been written. Requires the `std` feature.
+#[cfg(feature = "std")]
+pub fn export_json_lines<Fields, W, I, R>(writer: &mut W, records: I) -> io::Result<()>
+where
+    Fields: FieldSpans,
+    W: Write,
+    I: IntoIterator<Item = R>,
+    R: AsRef<[u8]>,
+{
+    for record in records {
+        let object = to_json_object::<Fields>(record.as_ref())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", err)))?;
+        writeln!(writer, "{}", object)?;
+    }
+    Ok(())
+}
+
+fn quote(name: &str) -> String {
+    let mut quoted = String::with_capacity(name.len() + 2);
+    quoted.push('"');
+    for c in name.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use fixtures::*;
+
+    #[test]
+    fn json_lines_export() {
+        type Fields = (Transport, AddressType, SecondByte);
+
+        assert_eq!(
+            to_json_object::<Fields>(&[0b0000_1001, 7]).unwrap(),
+            r#"{"Transport":1,"AddressType":2,"SecondByte":7}"#
+        );
+
+        let mut out = Vec::new();
+        export_json_lines::<Fields, _, _, _>(&mut out, vec![[0b01u8, 1], [0b0111, 255]]).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            concat!(
+                r#"{"Transport":1,"AddressType":0,"SecondByte":1}"#,
+                "\n",
+                r#"{"Transport":3,"AddressType":1,"SecondByte":255}"#,
+                "\n"
+            )
+        );
+
+        let mut out = Vec::new();
+        let err = export_json_lines::<Fields, _, _, _>(&mut out, vec![vec![0u8]]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(out.is_empty());
+    }
+}
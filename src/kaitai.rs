@@ -0,0 +1,213 @@
+//! Kaitai Struct export, so that other languages and the Kaitai IDE can read storages from
+//! the same field definitions.
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use bit_twiddles::BITS_PER_BYTE;
+
+use {Error, FieldSpans};
+
+/// Generates a Kaitai Struct spec with the given `id` reading the fields of `Fields`.
+///
+/// The spec uses `bit-endian: le`, matching the crate's LSB-first bit order, and reads fields
+/// in ascending bit order as `bN` integers. Gaps between fields become `reserved_<pos>`
+/// members. Member ids are the field type names in snake case.
+///
+/// Fails with [Error::FieldsOverlap] if two fields share bits, since a Kaitai `seq` can only
+/// describe consecutive members, and with [Error::Overflow] for fields wider than 8 bits, as
+/// the accessors do. Kaitai ids must be unique and match `[a-z][a-z0-9_]*`: an `id` or member
+/// id that does not match fails with [Error::InvalidIdentifier] and a repeated one with
+/// [Error::DuplicateIdentifier]. Zero-width fields have no Kaitai type and fail with
+/// [Error::ZeroWidthField].
+pub fn to_kaitai_yaml<Fields: FieldSpans>(id: &str) -> Result<String, Error> {
+    Fields::check_disjoint()?;
+    let spans = Fields::spans_by_pos();
+
+    check_id(id)?;
+    let mut yaml = format!("meta:\n  id: {}\n  bit-endian: le\nseq:\n", id);
+    let mut members = Vec::new();
+    let mut cursor = 0;
+    for span in spans {
+        if span.width > BITS_PER_BYTE {
+            return Err(Error::Overflow);
+        }
+        if span.width == 0 {
+            return Err(Error::ZeroWidthField(span.name));
+        }
+        if span.pos > cursor {
+            push_member(
+                &mut yaml,
+                &mut members,
+                format!("reserved_{}", cursor),
+                span.pos - cursor,
+            )?;
+        }
+        let member = snake_case(span.name);
+        check_id(&member)?;
+        push_member(&mut yaml, &mut members, member, span.width)?;
+        cursor = span.pos + span.width;
+    }
+    Ok(yaml)
+}
+
+fn check_id(id: &str) -> Result<(), Error> {
+    let mut chars = id.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_lowercase())
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::InvalidIdentifier(id.to_string()))
+    }
+}
+
+fn push_member(
+    yaml: &mut String,
+    members: &mut Vec<String>,
+    id: String,
+    width: usize,
+) -> Result<(), Error> {
+    if members.contains(&id) {
+        return Err(Error::DuplicateIdentifier(id));
+    }
+    yaml.push_str(&format!("  - id: {}\n    type: b{}\n", id, width));
+    members.push(id);
+    Ok(())
+}
+
+/// Converts a type name such as `AddressType` into an identifier such as `address_type`.
+pub(crate) fn snake_case(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len() + 4);
+    let mut prev_lower = false;
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            if prev_lower {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_lowercase());
+            prev_lower = false;
+        } else if c.is_ascii_alphanumeric() {
+            snake.push(c);
+            prev_lower = true;
+        } else if !snake.ends_with('_') {
+            snake.push('_');
+            prev_lower = false;
+        }
+    }
+    snake.trim_end_matches('_').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixtures::*;
+    use {BitField, FieldSpan};
+
+    #[test]
+    fn kaitai_export() {
+        assert_eq!(
+            to_kaitai_yaml::<(SecondByte, Transport, AddressType)>("actor_path_header").unwrap(),
+            "meta:
+  id: actor_path_header
+  bit-endian: le
+seq:
+  - id: transport
+    type: b2
+  - id: address_type
+    type: b2
+  - id: reserved_4
+    type: b4
+  - id: second_byte
+    type: b8
+"
+        );
+        assert_eq!(
+            to_kaitai_yaml::<(Transport, Straddling)>("broken"),
+            Err(Error::FieldsOverlap(
+                FieldSpan::of::<Transport>(),
+                FieldSpan::of::<Straddling>()
+            ))
+        );
+    }
+
+    #[test]
+    fn kaitai_export_rejects_invalid_specs() {
+        /// Claims no bits at all
+        #[derive(Debug)]
+        struct Marker;
+
+        impl BitField for Marker {
+            const POS: usize = 8;
+            const WIDTH: usize = 0;
+        }
+
+        assert_eq!(
+            to_kaitai_yaml::<(Transport,)>("Bad Id"),
+            Err(Error::InvalidIdentifier("Bad Id".to_string()))
+        );
+        assert_eq!(
+            to_kaitai_yaml::<(Transport,)>("2fast"),
+            Err(Error::InvalidIdentifier("2fast".to_string()))
+        );
+        assert_eq!(
+            to_kaitai_yaml::<(Transport, Marker)>("marked"),
+            Err(Error::ZeroWidthField("Marker"))
+        );
+    }
+
+    #[test]
+    fn kaitai_export_rejects_duplicate_ids() {
+        mod v2 {
+            use BitField;
+
+            /// Same name as the v1 field, one byte further up
+            #[derive(Debug)]
+            pub struct Transport;
+
+            impl BitField for Transport {
+                const POS: usize = 8;
+                const WIDTH: usize = 2;
+            }
+        }
+
+        /// Named like the gap member generated for bits 4 to 7
+        #[derive(Debug)]
+        #[allow(non_camel_case_types)]
+        struct Reserved_4;
+
+        impl BitField for Reserved_4 {
+            const POS: usize = 8;
+            const WIDTH: usize = 4;
+        }
+
+        assert_eq!(
+            to_kaitai_yaml::<(Transport, v2::Transport)>("p"),
+            Err(Error::DuplicateIdentifier("transport".to_string()))
+        );
+        assert_eq!(
+            to_kaitai_yaml::<(Transport, AddressType, Reserved_4)>("p"),
+            Err(Error::DuplicateIdentifier("reserved_4".to_string()))
+        );
+    }
+
+    #[test]
+    fn kaitai_export_rejects_wide_fields() {
+        /// Wider than its `u8` representation
+        #[derive(Debug)]
+        struct Wide;
+
+        impl BitField for Wide {
+            const POS: usize = 0;
+            const WIDTH: usize = 30;
+        }
+
+        assert_eq!(to_kaitai_yaml::<(Wide,)>("p"), Err(Error::Overflow));
+    }
+
+    #[test]
+    fn snake_case_names() {
+        assert_eq!(snake_case("AddressType"), "address_type");
+        assert_eq!(snake_case("IPv4"), "ipv4");
+        assert_eq!(snake_case("Addr<8>"), "addr_8");
+        assert_eq!(snake_case("Flags"), "flags");
+    }
+}
@@ -1,104 +1,920 @@
 //! Helpers for grouping together data in sub-byte bitfields.
-use std::convert::TryFrom;
-use std::fmt::Debug;
+//!
+//! Field types only need `Into<u8>` for [BitFieldExt::store] and `TryFrom<u8>` for
+//! [BitFieldExt::get_as], or the same conversions for whichever [BitStorage] integer they
+//! declare as their `BitField<Repr>`. Enums deriving `IntoPrimitive` and `TryFromPrimitive` from the
+//! `num_enum` crate get both impls for free, with no hand-written conversions.
+//!
+//! With the `derive` feature, structs whose members are all [BitField]s can derive
+//! [FromBitFields] and [IntoBitFields].
+//!
+//! The crate is `no_std` with `alloc` when the default `std` feature is disabled.
+//!
+//! # Wide fields
+//!
+//! Fields wider than 8 bits work with [BitFieldExt], [atomic::AtomicBitFieldExt] and the
+//! derives. Everything else takes `BitField<u8>` fields only: the tuple tooling built on
+//! [FieldSpans], [FieldSpan], [mask_of] and the [ct], [capabilities] and [testing] modules.
+#![no_std]
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt::Debug;
+
+#[macro_use]
+extern crate alloc;
+#[cfg(any(feature = "std", test))]
+extern crate std;
+
+#[cfg(feature = "derive")]
+extern crate bitfields_derive;
+#[cfg(feature = "nom")]
+extern crate nom;
+#[cfg(test)]
+extern crate num_enum;
+#[cfg(feature = "serde")]
+extern crate serde;
+
+#[cfg(feature = "derive")]
+pub use bitfields_derive::{BitEnum, FromBitFields, IntoBitFields};
+
+pub mod atomic;
+pub mod bank;
+pub mod capabilities;
+pub mod csv;
+pub mod ct;
+pub mod delta;
+pub mod diff;
+pub mod dump;
+#[cfg(test)]
+mod fixtures;
+pub mod jsonl;
+pub mod kaitai;
+#[cfg(feature = "nom")]
+pub mod nom_parsers;
+pub mod packing;
+pub mod remap;
+pub mod runs;
+#[cfg(feature = "serde")]
+pub mod serde_fields;
+pub mod testing;
+pub mod wireshark;
 
 type Pos = usize;
 type Width = usize;
 
 #[derive(Debug, PartialEq)]
+#[non_exhaustive]
 pub enum Error {
+    /// The field is wider than its representation type.
     Overflow,
-    TryFromErr,
+    /// The storage is too short to hold the field at its position.
+    InsufficientBytes,
+    /// The value's representation has bits set above the field's width.
+    ValueOutOfRange,
+    /// The stored representation is not a valid value of the field type.
+    TryFromErr {
+        /// The raw bits read from the field.
+        raw: u128,
+        /// The largest representation that fits in the field's width.
+        max: u128,
+        /// Name of the type the bits failed to convert into.
+        target: &'static str,
+    },
+    /// The field's [BitField::validate] hook rejected the representation.
+    ValidationFailed(String),
+    /// The two fields or storages involved in an operation do not have the same width.
+    WidthMismatch,
+    /// The two fields involved in an operation share one or more bits.
+    WouldOverlap,
+    /// Two fields of a tuple share one or more bits, in tuple order.
+    FieldsOverlap(FieldSpan, FieldSpan),
+    /// Data was left over after the last expected bit.
+    TrailingBits,
+    /// The named field holds different non-zero values on the two sides of a merge.
+    FieldConflict(&'static str),
+    /// A bit outside every known field is set.
+    UnknownBitsSet {
+        /// Index of the lowest such bit.
+        bit: usize,
+    },
+    /// The name cannot be used as an identifier in the generated output.
+    InvalidIdentifier(String),
+    /// Two fields, or a field and a generated member, map to the same identifier in the
+    /// generated output.
+    DuplicateIdentifier(String),
+    /// The named field is zero bits wide, which the generated output cannot express.
+    ZeroWidthField(&'static str),
 }
 
-// TODO  support &[u8]
-/// Helper trait for defining where in the [BitFieldSet] to put data.
+/// Helper trait for defining where in the storage to put data.
 ///
-/// # Panics
-/// Currently, the `WIDTH` field is used in the internal bit-shifting on bytes, meaning
-/// that it will panic if the `WIDTH` > 8.
+/// `Repr` is the integer the field converts to and from, and bounds its `WIDTH`: `u8` for
+/// sub-byte fields, up to `u128` for wide registers. See [BitStorage].
 pub trait BitField<Repr: Sized = u8>: Debug {
     const POS: Pos;
     const WIDTH: Width;
+
+    /// Checks constraints on the representation that cannot be expressed by the width alone,
+    /// such as "must be a power of two" or "must always be 4". Accepts everything by default.
+    ///
+    /// Only the typed writes run it: [BitFieldExt::store] and [BitFieldExt::store_with],
+    /// [BitFieldExt::update], the flag setters, [IntoBitFields] and
+    /// [AtomicBitFieldExt::store_field](atomic::AtomicBitFieldExt::store_field). Zeroing,
+    /// [BitFieldExt::swap_fields] and the raw-bit writes of [remap::remap],
+    /// [diff::merge_fields] and [ct::store] bypass it, so a field that must keep a fixed value
+    /// is only guaranteed to after [BitFieldExt::validate] passes. Reserved bits that must stay
+    /// zero are checked by [StorageExt::check_unknown_bits] instead.
+    fn validate(_repr: Repr) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Name of the field in tooling output such as CSV headers, JSON keys and generated specs.
+    /// Defaults to the type name without its module path, so override it to tell apart
+    /// same-named fields from different modules, e.g. `ctrl::Mode` and `status::Mode`.
+    fn name() -> &'static str {
+        let full = core::any::type_name::<Self>();
+        let path = &full[..full.find('<').unwrap_or(full.len())];
+        let start = path.rfind("::").map_or(0, |idx| idx + 2);
+        &full[start..]
+    }
+}
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// The unsigned integers a [BitField] can be represented as: `u8`, `u16`, `u32`, `u64` and
+/// `u128`. Sealed, since the accessors move bits through a `u128`.
+pub trait BitStorage: Copy + Debug + private::Sealed {
+    /// Number of bits in the integer, the widest `WIDTH` a field of this repr can have.
+    const BITS: usize;
+
+    /// Zero-extends the integer.
+    fn to_bits(self) -> u128;
+    /// Truncates `bits` to the integer's width.
+    fn from_bits(bits: u128) -> Self;
+}
+
+macro_rules! impl_bit_storage {
+    ($($ty:ty),+) => {
+        $(
+            impl private::Sealed for $ty {}
+
+            impl BitStorage for $ty {
+                const BITS: usize = <$ty>::BITS as usize;
+
+                fn to_bits(self) -> u128 {
+                    self as u128
+                }
+
+                fn from_bits(bits: u128) -> Self {
+                    bits as $ty
+                }
+            }
+        )+
+    };
+}
+
+impl_bit_storage!(u8, u16, u32, u64, u128);
+
+/// What to do when a value does not fit in the narrower field it is stored or remapped into,
+/// see [BitFieldExt::store_with] and [remap::remap].
+///
+/// Values moving into a wider field are always zero-extended.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WidthPolicy {
+    /// Fail with [Error::ValueOutOfRange].
+    Strict,
+    /// Keep only the low bits that fit.
+    Truncate,
+    /// Clamp to the largest value that fits.
+    Saturate,
 }
 
-pub trait BitFieldExt {
+impl WidthPolicy {
+    /// Applies the policy to `bits` about to be written into a `width`-bit field.
+    pub(crate) fn fit(self, bits: u128, width: usize) -> Result<u128, Error> {
+        let max = max_bits(width);
+        if bits <= max {
+            return Ok(bits);
+        }
+        match self {
+            WidthPolicy::Strict => Err(Error::ValueOutOfRange),
+            WidthPolicy::Truncate => Ok(bits & max),
+            WidthPolicy::Saturate => Ok(max),
+        }
+    }
+}
+
+/// Per-field accessors on a byte storage. `Repr` is inferred from the field type's
+/// [BitField] impl, so `storage.get_as::<Transport>()` needs no annotation.
+pub trait BitFieldExt<Repr: BitStorage = u8> {
     fn store<Field>(&mut self, field: Field) -> Result<(), Error>
     where
-        Field: BitField<u8> + Into<u8>;
+        Field: BitField<Repr> + Into<Repr>;
+    /// Like [BitFieldExt::store], with `policy` choosing what happens to representations wider
+    /// than the field. `store` is `store_with(field, WidthPolicy::Strict)`.
+    fn store_with<Field>(&mut self, field: Field, policy: WidthPolicy) -> Result<(), Error>
+    where
+        Field: BitField<Repr> + Into<Repr>;
     fn get_as<Field>(&self) -> Result<Field, Error>
     where
-        Field: BitField<u8> + TryFrom<u8>;
+        Field: BitField<Repr> + TryFrom<Repr>;
+    /// Like [BitFieldExt::get_as], but falls back to `default` when the stored bits are not a
+    /// valid `Field`. Errors about the field not fitting the storage are still returned.
+    fn get_as_or<Field>(&self, default: Field) -> Result<Field, Error>
+    where
+        Field: BitField<Repr> + TryFrom<Repr>;
     fn check_field<Field>(&self) -> Result<(), Error>
     where
-        Field: BitField<u8>;
+        Field: BitField<Repr>;
+    /// Exchanges the stored bits of two equal-width, non-overlapping fields.
+    fn swap_fields<A, B>(&mut self) -> Result<(), Error>
+    where
+        A: BitField<Repr>,
+        B: BitField<Repr>;
+    /// Runs the field's [BitField::validate] hook against its currently stored bits.
+    fn validate<Field>(&self) -> Result<(), Error>
+    where
+        Field: BitField<Repr>;
+    /// Reads the field, passes it through `f` and stores the result, e.g.
+    /// `update::<Counter>(|c| Counter(c.0 + 1))`. The storage is left untouched if either the
+    /// read or the write fails.
+    fn update<Field>(&mut self, f: impl FnOnce(Field) -> Field) -> Result<(), Error>
+    where
+        Field: BitField<Repr> + TryFrom<Repr> + Into<Repr>;
+    /// Zeroes the field's bits, without running [BitField::validate] since zero need not be a
+    /// valid value.
+    fn clear_field<Field>(&mut self) -> Result<(), Error>
+    where
+        Field: BitField<Repr>;
+    /// Reads a one-bit field as a `bool`. Fails with [Error::WidthMismatch] if `Flag` is wider.
+    fn get_flag<Flag>(&self) -> Result<bool, Error>
+    where
+        Flag: BitField<Repr>;
+    /// Sets or unsets a one-bit field, running its [BitField::validate] hook like
+    /// [BitFieldExt::store] does.
+    fn set_flag<Flag>(&mut self, value: bool) -> Result<(), Error>
+    where
+        Flag: BitField<Repr>;
+    /// Flips a one-bit field, see [BitFieldExt::set_flag].
+    fn toggle_flag<Flag>(&mut self) -> Result<(), Error>
+    where
+        Flag: BitField<Repr>;
+}
+
+/// Operations on a byte storage as a whole, or on several fields at once.
+pub trait StorageExt {
+    /// Decodes several fields in one call, e.g. `get_all::<(Transport, AddressType)>()`.
+    fn get_all<Fields>(&self) -> Result<Fields, Error>
+    where
+        Fields: FieldTuple;
+    /// Builds a `T` from the fields in this storage, see [FromBitFields].
+    fn decode<T>(&self) -> Result<T, Error>
+    where
+        T: FromBitFields;
+    /// Writes the members of `value` into their fields in this storage, see [IntoBitFields].
+    fn encode<T>(&mut self, value: T) -> Result<(), Error>
+    where
+        T: IntoBitFields;
+    /// Mirrors the bit order of the whole storage, so that bit `i` becomes
+    /// bit `8 * len - 1 - i`.
+    ///
+    /// A field stored at `POS` with `WIDTH` ends up at `8 * len - POS - WIDTH`
+    /// with its own bits reversed, which is how LSB-first serial data maps onto
+    /// MSB-first protocol definitions.
+    fn reverse_bits(&mut self);
+    /// Reverses the byte order within each `word_len`-byte word of the storage, converting a
+    /// big-endian buffer into its little-endian equivalent and back.
+    ///
+    /// Fields are addressed by bit index `8 * byte + bit`, so after the swap a layout written
+    /// against the little-endian word reads the same values the big-endian buffer carried.
+    /// Fails with [Error::InsufficientBytes] if the last word is incomplete.
+    ///
+    /// # Panics
+    /// Panics if `word_len` is 0.
+    fn swap_byte_order(&mut self, word_len: usize) -> Result<(), Error>;
+    /// Iterates over the runs of consecutive set and unset bits in the whole storage.
+    fn bit_runs(&self) -> runs::BitRuns<'_>;
+    /// Collects the bit runs inside each field of `Fields`, in tuple order. Runs never cross
+    /// a field boundary and unclaimed bits are skipped.
+    fn field_bit_runs<Fields>(&self) -> Result<Vec<runs::BitRun>, Error>
+    where
+        Fields: FieldSpans;
+    /// The raw bits of each field of `Fields` next to its span, in ascending bit order.
+    fn field_values<Fields>(&self) -> Result<Vec<(FieldSpan, u8)>, Error>
+    where
+        Fields: FieldSpans;
+    /// Zeroes every field of `Fields`, leaving unclaimed bits as they are and, like
+    /// [BitFieldExt::clear_field], without running [BitField::validate]. Nothing is cleared if
+    /// any field does not fit the storage.
+    fn clear_fields<Fields>(&mut self) -> Result<(), Error>
+    where
+        Fields: FieldSpans;
+    /// Fails with [Error::UnknownBitsSet] if any bit outside the fields of `Fields` is set, for
+    /// rejecting untrusted data whose reserved bits must be zero.
+    fn check_unknown_bits<Fields>(&self) -> Result<(), Error>
+    where
+        Fields: FieldSpans;
+    /// Sets or unsets every one-bit field of `Flags` at once, running each flag's
+    /// [BitField::validate] hook like [BitFieldExt::set_flag] does. Nothing is written if any
+    /// of them is wider than one bit, does not fit the storage or rejects the value.
+    fn set_flags<Flags>(&mut self, value: bool) -> Result<(), Error>
+    where
+        Flags: FieldSpans;
 }
 
-impl BitFieldExt for [u8] {
+impl<Repr: BitStorage> BitFieldExt<Repr> for [u8] {
     fn store<Field>(&mut self, field: Field) -> Result<(), Error>
     where
-        Field: BitField<u8> + Into<u8>,
+        Field: BitField<Repr> + Into<Repr>,
     {
-        use bit_twiddles::*;
+        self.store_with(field, WidthPolicy::Strict)
+    }
 
+    fn store_with<Field>(&mut self, field: Field, policy: WidthPolicy) -> Result<(), Error>
+    where
+        Field: BitField<Repr> + Into<Repr>,
+    {
         self.check_field::<Field>()?;
+        let bits = policy.fit(field.into().to_bits(), Field::WIDTH)?;
+        Field::validate(Repr::from_bits(bits)).map_err(Error::ValidationFailed)?;
+        write_bits(self, Field::POS, Field::WIDTH, bits);
+        Ok(())
+    }
 
-        let pos = Field::POS;
-        let width = Field::WIDTH;
+    fn get_as<Field>(&self) -> Result<Field, Error>
+    where
+        Field: BitField<Repr> + TryFrom<Repr>,
+    {
+        self.check_field::<Field>()?;
+        let raw = read_bits(self, Field::POS, Field::WIDTH);
+        Field::try_from(Repr::from_bits(raw)).map_err(|_| Error::TryFromErr {
+            raw,
+            max: max_bits(Field::WIDTH),
+            target: core::any::type_name::<Field>(),
+        })
+    }
 
-        let repr: u8 = field.into();
-        (0..width).for_each(|i| {
-            let idx = pos + i;
-            let (byte, bit) = (idx / 8, idx % 8);
-            set_bit_to(&mut self[byte], bit, get_bit(&repr, i));
-        });
+    fn get_as_or<Field>(&self, default: Field) -> Result<Field, Error>
+    where
+        Field: BitField<Repr> + TryFrom<Repr>,
+    {
+        match self.get_as::<Field>() {
+            Err(Error::TryFromErr { .. }) => Ok(default),
+            other => other,
+        }
+    }
+
+    fn check_field<Field>(&self) -> Result<(), Error>
+    where
+        Field: BitField<Repr>,
+    {
+        check_bits(self, Field::POS, Field::WIDTH, Repr::BITS)
+    }
+
+    fn swap_fields<A, B>(&mut self) -> Result<(), Error>
+    where
+        A: BitField<Repr>,
+        B: BitField<Repr>,
+    {
+        self.check_field::<A>()?;
+        self.check_field::<B>()?;
+
+        if A::WIDTH != B::WIDTH {
+            return Err(Error::WidthMismatch);
+        }
+        if A::POS == B::POS {
+            // Same bits on both sides, nothing to exchange
+            return Ok(());
+        }
+        if A::POS < B::POS + B::WIDTH && B::POS < A::POS + A::WIDTH {
+            return Err(Error::WouldOverlap);
+        }
+
+        let a = read_bits(self, A::POS, A::WIDTH);
+        let b = read_bits(self, B::POS, B::WIDTH);
+        write_bits(self, A::POS, A::WIDTH, b);
+        write_bits(self, B::POS, B::WIDTH, a);
         Ok(())
     }
 
-    fn get_as<Field>(&self) -> Result<Field, Error>
+    fn validate<Field>(&self) -> Result<(), Error>
     where
-        Field: BitField<u8> + TryFrom<u8>,
+        Field: BitField<Repr>,
     {
-        use bit_twiddles::*;
+        self.check_field::<Field>()?;
+        let repr = Repr::from_bits(read_bits(self, Field::POS, Field::WIDTH));
+        Field::validate(repr).map_err(Error::ValidationFailed)
+    }
+
+    fn update<Field>(&mut self, f: impl FnOnce(Field) -> Field) -> Result<(), Error>
+    where
+        Field: BitField<Repr> + TryFrom<Repr> + Into<Repr>,
+    {
+        let field = self.get_as::<Field>()?;
+        self.store(f(field))
+    }
 
+    fn clear_field<Field>(&mut self) -> Result<(), Error>
+    where
+        Field: BitField<Repr>,
+    {
         self.check_field::<Field>()?;
+        write_bits(self, Field::POS, Field::WIDTH, 0);
+        Ok(())
+    }
 
-        let pos = Field::POS;
-        let width = Field::WIDTH;
+    fn get_flag<Flag>(&self) -> Result<bool, Error>
+    where
+        Flag: BitField<Repr>,
+    {
+        check_flag(self, Flag::POS, Flag::WIDTH)?;
+        Ok(read_bits(self, Flag::POS, 1) == 1)
+    }
 
-        let mut repr = 0_u8;
-        (0..width).for_each(|i| {
-            let idx = pos + i;
-            let byte = idx / 8;
-            let bit = idx % 8;
-            set_bit_to(&mut repr, i, get_bit(&self[byte], bit));
-        });
+    fn set_flag<Flag>(&mut self, value: bool) -> Result<(), Error>
+    where
+        Flag: BitField<Repr>,
+    {
+        check_flag(self, Flag::POS, Flag::WIDTH)?;
+        Flag::validate(Repr::from_bits(value.into())).map_err(Error::ValidationFailed)?;
+        write_bits(self, Flag::POS, 1, value.into());
+        Ok(())
+    }
 
-        Field::try_from(repr).map_err(|_| Error::TryFromErr)
+    fn toggle_flag<Flag>(&mut self) -> Result<(), Error>
+    where
+        Flag: BitField<Repr>,
+    {
+        let value = self.get_flag::<Flag>()?;
+        self.set_flag::<Flag>(!value)
     }
+}
 
-    fn check_field<Field>(&self) -> Result<(), Error>
+impl StorageExt for [u8] {
+    fn get_all<Fields>(&self) -> Result<Fields, Error>
+    where
+        Fields: FieldTuple,
+    {
+        Fields::get_from(self)
+    }
+
+    fn decode<T>(&self) -> Result<T, Error>
+    where
+        T: FromBitFields,
+    {
+        T::from_fields(self)
+    }
+
+    fn encode<T>(&mut self, value: T) -> Result<(), Error>
     where
-        Field: BitField<u8>,
+        T: IntoBitFields,
     {
-        use bit_twiddles::BITS_PER_BYTE;
+        value.into_fields(self)
+    }
+
+    fn reverse_bits(&mut self) {
+        self.reverse();
+        self.iter_mut().for_each(|byte| *byte = byte.reverse_bits());
+    }
 
-        if Field::WIDTH > BITS_PER_BYTE {
-            return Err(Error::Overflow);
+    fn swap_byte_order(&mut self, word_len: usize) -> Result<(), Error> {
+        assert!(word_len > 0, "word length must be non-zero");
+        if !self.len().is_multiple_of(word_len) {
+            return Err(Error::InsufficientBytes);
         }
-        let supported_bits = BITS_PER_BYTE * self.len();
-        if (Field::POS + Field::WIDTH) > supported_bits {
-            return Err(Error::Overflow);
-        } else {
-            return Ok(());
+        self.chunks_exact_mut(word_len)
+            .for_each(|word| word.reverse());
+        Ok(())
+    }
+
+    fn bit_runs(&self) -> runs::BitRuns<'_> {
+        runs::BitRuns::new(self, 0, bit_twiddles::BITS_PER_BYTE * self.len())
+    }
+
+    fn field_bit_runs<Fields>(&self) -> Result<Vec<runs::BitRun>, Error>
+    where
+        Fields: FieldSpans,
+    {
+        let mut runs = Vec::new();
+        for span in Fields::spans() {
+            check_span(self, span.pos, span.width)?;
+            runs.extend(runs::BitRuns::new(self, span.pos, span.pos + span.width));
+        }
+        Ok(runs)
+    }
+
+    fn field_values<Fields>(&self) -> Result<Vec<(FieldSpan, u8)>, Error>
+    where
+        Fields: FieldSpans,
+    {
+        Fields::spans_by_pos()
+            .into_iter()
+            .map(|span| Ok((span, span.read(self)?)))
+            .collect()
+    }
+
+    fn clear_fields<Fields>(&mut self) -> Result<(), Error>
+    where
+        Fields: FieldSpans,
+    {
+        let spans = Fields::spans();
+        for span in &spans {
+            check_span(self, span.pos, span.width)?;
+        }
+        for span in spans {
+            write_bits(self, span.pos, span.width, 0);
+        }
+        Ok(())
+    }
+
+    fn check_unknown_bits<Fields>(&self) -> Result<(), Error>
+    where
+        Fields: FieldSpans,
+    {
+        let spans = Fields::spans();
+        let unknown = (0..bit_twiddles::BITS_PER_BYTE * self.len()).find(|&bit| {
+            let (byte, offset) = bit_twiddles::byte_bit_offset(bit);
+            bit_twiddles::test_bit(&self[byte], offset)
+                && !spans.iter().any(|span| span.contains(bit))
+        });
+        match unknown {
+            Some(bit) => Err(Error::UnknownBitsSet { bit }),
+            None => Ok(()),
+        }
+    }
+
+    fn set_flags<Flags>(&mut self, value: bool) -> Result<(), Error>
+    where
+        Flags: FieldSpans,
+    {
+        let spans = Flags::spans();
+        for span in &spans {
+            check_flag(self, span.pos, span.width)?;
+        }
+        Flags::validate_each(value.into())?;
+        for span in spans {
+            write_bits(self, span.pos, 1, value.into());
+        }
+        Ok(())
+    }
+}
+
+/// Rounds `pos` up to the next multiple of `alignment`, for declaring fields that must start
+/// on a nibble or byte boundary after a previous field:
+///
+/// ```
+/// # use bitfields::{pad_to, BitField};
+/// # #[derive(Debug)] struct Flags;
+/// # impl BitField for Flags { const POS: usize = 0; const WIDTH: usize = 3; }
+/// #[derive(Debug)]
+/// struct Length;
+///
+/// impl BitField for Length {
+///     const POS: usize = pad_to(Flags::POS + Flags::WIDTH, 4);
+///     const WIDTH: usize = 4;
+/// }
+/// assert_eq!(Length::POS, 4);
+/// ```
+///
+/// # Panics
+/// Panics if `alignment` is 0.
+pub const fn pad_to(pos: Pos, alignment: usize) -> Pos {
+    pos.div_ceil(alignment) * alignment
+}
+
+/// The mask covering `Field` in place, reading the storage as a little-endian integer where
+/// storage bit `n` is bit `n` of the integer. See [wide fields](crate#wide-fields).
+///
+/// # Panics
+/// Panics, or fails to compile in const contexts, if `Field` reaches past bit 63.
+pub const fn mask_of<Field: BitField<u8>>() -> u64 {
+    assert!(
+        Field::POS + Field::WIDTH <= 64,
+        "field does not fit in a 64-bit mask"
+    );
+    if Field::WIDTH == 0 {
+        0
+    } else {
+        shifted_mask_of::<Field>() << Field::POS
+    }
+}
+
+/// The mask covering `Field` shifted down to bit 0, i.e. its `WIDTH` low bits.
+pub const fn shifted_mask_of<Field: BitField<u8>>() -> u64 {
+    if Field::WIDTH >= 64 {
+        u64::MAX
+    } else {
+        (1 << Field::WIDTH) - 1
+    }
+}
+
+/// Fieldless enums stored in a [BitField], so that the field's width can be computed from the
+/// variants with [width_of] instead of being kept in sync by hand:
+///
+/// ```
+/// # use bitfields::{width_of, BitEnum, BitField};
+/// #[derive(Debug)]
+/// #[repr(u8)]
+/// enum Mode {
+///     Idle = 0,
+///     Run = 1,
+///     Fault = 5,
+/// }
+///
+/// impl BitEnum for Mode {
+///     const MAX_DISCRIMINANT: u128 = Mode::Fault as u128;
+/// }
+///
+/// impl BitField for Mode {
+///     const POS: usize = 0;
+///     const WIDTH: usize = width_of::<Mode>();
+/// }
+/// assert_eq!(Mode::WIDTH, 3);
+/// ```
+///
+/// With the `derive` feature, `#[derive(BitEnum)]` fills in `MAX_DISCRIMINANT`.
+pub trait BitEnum {
+    /// The largest discriminant among the enum's variants.
+    const MAX_DISCRIMINANT: u128;
+}
+
+/// The number of bits needed to hold every discriminant of `E`.
+pub const fn width_of<E: BitEnum>() -> Width {
+    (u128::BITS - E::MAX_DISCRIMINANT.leading_zeros()) as Width
+}
+
+/// A tuple of [BitField] types describing a layout, for tooling that only needs the fields'
+/// positions and widths and never decodes them into their types.
+///
+/// Implemented for tuples of up to eight `BitField<u8>` fields. Wider fields could share a
+/// `Repr` parameter on the trait, but every function taking a tuple would then need a second
+/// type argument, as in `to_csv_row::<Fields, _>`, so the tuple tooling keeps to `u8`. See
+/// [wide fields](crate#wide-fields).
+pub trait FieldSpans {
+    /// Positions, widths and names of the tuple's fields, in tuple order.
+    fn spans() -> Vec<FieldSpan>;
+    /// Runs the [BitField::validate] hook of every field on `repr`, in tuple order.
+    fn validate_each(repr: u8) -> Result<(), Error>;
+
+    /// [FieldSpans::spans] sorted by position, lowest bit first.
+    fn spans_by_pos() -> Vec<FieldSpan> {
+        let mut spans = Self::spans();
+        spans.sort_by_key(|span| span.pos);
+        spans
+    }
+
+    /// The field that owns storage bit `bit`, if any.
+    fn field_at_bit(bit: usize) -> Option<FieldSpan> {
+        Self::spans().into_iter().find(|span| span.contains(bit))
+    }
+
+    /// Number of bits below the lowest field.
+    fn trailing_unused_bits() -> usize {
+        Self::spans().iter().map(|span| span.pos).min().unwrap_or(0)
+    }
+
+    /// Number of bits above the highest field in a storage of `num_bits` bits.
+    fn leading_unused_bits(num_bits: usize) -> usize {
+        num_bits.saturating_sub(end_bit(&Self::spans()))
+    }
+
+    /// Smallest storage length, in bytes, that holds every field.
+    fn min_storage_len() -> usize {
+        end_bit(&Self::spans()).div_ceil(bit_twiddles::BITS_PER_BYTE)
+    }
+
+    /// The first two fields, in tuple order, that share one or more bits.
+    fn first_overlap() -> Option<(FieldSpan, FieldSpan)> {
+        let spans = Self::spans();
+        spans.iter().enumerate().find_map(|(idx, a)| {
+            spans[idx + 1..]
+                .iter()
+                .find(|b| a.overlaps(b))
+                .map(|b| (*a, *b))
+        })
+    }
+
+    /// Fails with [Error::FieldsOverlap] on the [first overlap](FieldSpans::first_overlap),
+    /// if any two fields share bits.
+    fn check_disjoint() -> Result<(), Error> {
+        match Self::first_overlap() {
+            Some((a, b)) => Err(Error::FieldsOverlap(a, b)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A [FieldSpans] tuple whose fields can also be decoded together through
+/// [StorageExt::get_all]. See [wide fields](crate#wide-fields).
+pub trait FieldTuple: FieldSpans + Sized {
+    /// Decodes every field of the tuple, failing on the first field that cannot be read.
+    fn get_from(storage: &[u8]) -> Result<Self, Error>;
+}
+
+/// One past the highest bit covered by `spans`.
+fn end_bit(spans: &[FieldSpan]) -> usize {
+    spans
+        .iter()
+        .map(|span| span.pos + span.width)
+        .max()
+        .unwrap_or(0)
+}
+
+macro_rules! impl_field_tuple {
+    ($($field:ident),+) => {
+        impl<$($field),+> FieldSpans for ($($field,)+)
+        where
+            $($field: BitField<u8>),+
+        {
+            fn spans() -> Vec<FieldSpan> {
+                vec![$(FieldSpan::of::<$field>()),+]
+            }
+
+            fn validate_each(repr: u8) -> Result<(), Error> {
+                $($field::validate(repr).map_err(Error::ValidationFailed)?;)+
+                Ok(())
+            }
+        }
+
+        impl<$($field),+> FieldTuple for ($($field,)+)
+        where
+            $($field: BitField<u8> + TryFrom<u8>),+
+        {
+            fn get_from(storage: &[u8]) -> Result<Self, Error> {
+                Ok(($(storage.get_as::<$field>()?,)+))
+            }
+        }
+    };
+}
+
+impl_field_tuple!(A);
+impl_field_tuple!(A, B);
+impl_field_tuple!(A, B, C);
+impl_field_tuple!(A, B, C, D);
+impl_field_tuple!(A, B, C, D, E);
+impl_field_tuple!(A, B, C, D, E, F);
+impl_field_tuple!(A, B, C, D, E, F, G);
+impl_field_tuple!(A, B, C, D, E, F, G, H);
+
+/// The position and width of a [BitField] type, for tooling that walks the fields of a
+/// [FieldSpans] tuple without decoding them into their types.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FieldSpan {
+    /// The field's [BitField::name], by default its type name without the module path.
+    pub name: &'static str,
+    pub pos: Pos,
+    pub width: Width,
+}
+
+impl FieldSpan {
+    pub fn of<Field: BitField<u8>>() -> FieldSpan {
+        FieldSpan {
+            name: Field::name(),
+            pos: Field::POS,
+            width: Field::WIDTH,
         }
     }
+
+    /// Whether storage bit `bit` belongs to this span.
+    pub fn contains(&self, bit: usize) -> bool {
+        self.pos <= bit && bit < self.pos + self.width
+    }
+
+    /// Whether the two spans share at least one bit.
+    pub fn overlaps(&self, other: &FieldSpan) -> bool {
+        self.pos < other.pos + other.width && other.pos < self.pos + self.width
+    }
+
+    /// Reads the raw bits of this span from `storage`, see [wide fields](crate#wide-fields).
+    pub fn read(&self, storage: &[u8]) -> Result<u8, Error> {
+        check_span(storage, self.pos, self.width)?;
+        Ok(read_repr(storage, self.pos, self.width))
+    }
+}
+
+/// Domain types that can be built from the fields of a storage in one call, typically a
+/// struct grouping the decoded fields of a header.
+///
+/// Used through [StorageExt::decode].
+pub trait FromBitFields: Sized {
+    fn from_fields(storage: &[u8]) -> Result<Self, Error>;
+}
+
+/// The inverse of [FromBitFields]: domain types that write each of their members into the
+/// corresponding field of a storage.
+///
+/// Used through [StorageExt::encode]. Fields written before a failing one are left in place.
+pub trait IntoBitFields {
+    fn into_fields(self, storage: &mut [u8]) -> Result<(), Error>;
+}
+
+/// Support code for the `bitfields-derive` macros, not part of the public API.
+#[doc(hidden)]
+pub mod __private {
+    use {BitField, BitStorage};
+
+    /// Position and width of a derive member, failing const evaluation if the member is
+    /// wider than its representation.
+    pub const fn span<Repr: BitStorage, Field: BitField<Repr>>() -> (usize, usize) {
+        assert!(
+            Field::WIDTH <= Repr::BITS,
+            "bitfield is wider than its representation"
+        );
+        (Field::POS, Field::WIDTH)
+    }
+
+    /// Whether any two of the `(pos, width)` spans share a bit.
+    pub const fn any_overlap(spans: &[(usize, usize)]) -> bool {
+        let mut a = 0;
+        while a < spans.len() {
+            let mut b = a + 1;
+            while b < spans.len() {
+                let ((a_pos, a_width), (b_pos, b_width)) = (spans[a], spans[b]);
+                if a_pos < b_pos + b_width && b_pos < a_pos + a_width {
+                    return true;
+                }
+                b += 1;
+            }
+            a += 1;
+        }
+        false
+    }
+}
+
+/// Checks that `width` bits fit in a byte and that `storage` is long enough to hold them at `pos`.
+fn check_span(storage: &[u8], pos: Pos, width: Width) -> Result<(), Error> {
+    check_bits(storage, pos, width, bit_twiddles::BITS_PER_BYTE)
+}
+
+/// The largest value that fits in `width` bits, for widths up to 128.
+fn max_bits(width: Width) -> u128 {
+    u128::MAX
+        .checked_shr((u128::BITS as usize - width) as u32)
+        .unwrap_or(0)
+}
+
+/// Checks that a field is one bit wide and that `storage` holds it.
+fn check_flag(storage: &[u8], pos: Pos, width: Width) -> Result<(), Error> {
+    if width != 1 {
+        return Err(Error::WidthMismatch);
+    }
+    check_span(storage, pos, width)
+}
+
+/// Checks that `width` bits fit in a `repr_bits`-bit integer and that `storage` is long enough
+/// to hold them at `pos`.
+fn check_bits(storage: &[u8], pos: Pos, width: Width, repr_bits: usize) -> Result<(), Error> {
+    use bit_twiddles::BITS_PER_BYTE;
+
+    if width > repr_bits {
+        return Err(Error::Overflow);
+    }
+    let supported_bits = BITS_PER_BYTE * storage.len();
+    if (pos + width) > supported_bits {
+        Err(Error::InsufficientBytes)
+    } else {
+        Ok(())
+    }
+}
+
+/// Reads `width` bits starting at bit `pos` of `storage` into the low bits of a byte.
+fn read_repr(storage: &[u8], pos: Pos, width: Width) -> u8 {
+    read_bits(storage, pos, width) as u8
+}
+
+/// Reads up to 128 bits starting at bit `pos` of `storage` into the low bits of a `u128`.
+fn read_bits(storage: &[u8], pos: Pos, width: Width) -> u128 {
+    use bit_twiddles::*;
+
+    (0..width).fold(0, |bits, i| {
+        let (byte, bit) = byte_bit_offset(pos + i);
+        bits | (get_bit(&storage[byte], bit) as u128) << i
+    })
+}
+
+/// Writes the low `width` bits of `bits` into `storage`, starting at bit `pos`.
+fn write_bits(storage: &mut [u8], pos: Pos, width: Width, bits: u128) {
+    use bit_twiddles::*;
+
+    (0..width).for_each(|i| {
+        let (byte, bit) = byte_bit_offset(pos + i);
+        set_bit_to(&mut storage[byte], bit, ((bits >> i) & 0b1) as u8);
+    });
+}
+
+/// Writes the low `width` bits of `repr` into `storage`, starting at bit `pos`.
+fn write_repr(storage: &mut [u8], pos: Pos, width: Width, repr: u8) {
+    write_bits(storage, pos, width, repr.into());
 }
 
 /// Bit-twiddling helpers
 ///
 /// # Panics
 /// All of these will panic if the `pos` parameter exceeds 7
+#[allow(dead_code)]
 mod bit_twiddles {
     pub const BITS_PER_BYTE: usize = 8;
 
@@ -107,7 +923,7 @@ mod bit_twiddles {
     }
 
     pub fn get_bit(target: &u8, pos: usize) -> u8 {
-        ((target >> pos) & 0b1)
+        (target >> pos) & 0b1
     }
 
     pub fn test_bit(target: &u8, pos: usize) -> bool {
@@ -269,137 +1085,650 @@ mod bit_twiddles {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::string::ToString;
+    use fixtures::*;
+
+    #[test]
+    fn store_and_retrieve() {
+        use super::BitFieldExt;
+        let mut storage = [0u8, 0u8];
+        storage.store(Transport::TCP).unwrap();
+        assert_eq!(storage.get_as::<Transport>().unwrap(), Transport::TCP);
+    }
 
-    #[derive(Debug, PartialEq)]
-    #[repr(u8)]
-    enum Transport {
-        TCP = 0b01,
-        UDP = 0b10,
-        UDT = 0b11,
+    #[test]
+    fn overwrite() {
+        let mut storage = [0u8, 0u8];
+        storage.store(Transport::TCP).unwrap();
+        assert_eq!(storage.get_as::<Transport>().unwrap(), Transport::TCP);
+        storage.store(Transport::UDP).unwrap();
+        assert_eq!(storage.get_as::<Transport>().unwrap(), Transport::UDP);
     }
 
-    impl BitField for Transport {
-        const POS: usize = 0;
-        const WIDTH: usize = 2;
+    #[test]
+    fn store_too_wide() {
+        // small_storage is only 8 bits, and thus cannot fit the WideWithOffset
+        let mut small_storage = [0u8];
+        assert_eq!(
+            small_storage.store(WideWithOffset::A),
+            Err(Error::InsufficientBytes)
+        );
     }
 
-    impl Into<u8> for Transport {
-        fn into(self) -> u8 {
-            self as u8
+    #[test]
+    fn store_full_byte() {
+        let mut storage = [0u8, 0u8];
+        storage.store(SecondByte(0xFF)).unwrap();
+        assert_eq!(storage, [0x00, 0xFF]);
+        assert_eq!(storage.get_as::<SecondByte>().unwrap(), SecondByte(0xFF));
+    }
+
+    #[test]
+    fn store_out_of_range() {
+        // `WideWithOffset` representations do not fit in its 2-bit width
+        let mut storage = [0u8, 0u8];
+        assert_eq!(
+            storage.store(WideWithOffset::A),
+            Err(Error::ValueOutOfRange)
+        );
+        assert_eq!(storage, [0u8, 0u8]);
+    }
+
+    #[test]
+    fn corrupted_read() {
+        // 0b0000_0000 is not a valid representation for [Transport]
+        let storage = [0u8];
+        assert_eq!(
+            storage.get_as::<Transport>(),
+            Err(Error::TryFromErr {
+                raw: 0b00,
+                max: 0b11,
+                target: std::any::type_name::<Transport>(),
+            })
+        );
+    }
+
+    #[test]
+    fn get_with_default() {
+        let storage = [0u8];
+        assert_eq!(storage.get_as_or(Transport::UDT), Ok(Transport::UDT));
+
+        let storage = [0b01u8];
+        assert_eq!(storage.get_as_or(Transport::UDT), Ok(Transport::TCP));
+
+        // Layout errors are not masked by the default
+        assert_eq!(
+            storage.get_as_or(WideWithOffset::A),
+            Err(Error::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn invalid_field() {
+        let mut storage = [0u8, 0u8];
+        // `InvalidWidth` has a width of 9, exceeding the current allowed field width
+        assert_eq!(storage.store(InvalidWidth::DoNotCare), Err(Error::Overflow));
+        assert_eq!(storage.get_as::<InvalidWidth>(), Err(Error::Overflow));
+    }
+
+    #[test]
+    fn swap_fields() {
+        let mut storage = [0u8];
+        storage.store(Transport::TCP).unwrap();
+        storage.store(AddressType::DomainName).unwrap();
+        storage.swap_fields::<Transport, AddressType>().unwrap();
+        assert_eq!(storage.get_as::<Transport>().unwrap(), Transport::UDP);
+        assert_eq!(storage.get_as::<AddressType>().unwrap(), AddressType::IPv6);
+        assert_eq!(storage, [0b0000_0110]);
+    }
+
+    #[test]
+    fn swap_invalid_fields() {
+        let mut storage = [0b0000_0110u8];
+        assert_eq!(
+            storage.swap_fields::<Transport, ThreeWide>(),
+            Err(Error::WidthMismatch)
+        );
+        assert_eq!(
+            storage.swap_fields::<Transport, Straddling>(),
+            Err(Error::WouldOverlap)
+        );
+        assert_eq!(
+            storage.swap_fields::<Transport, WideWithOffset>(),
+            Err(Error::InsufficientBytes)
+        );
+        // Failed swaps leave the storage untouched
+        assert_eq!(storage, [0b0000_0110]);
+    }
+
+    #[test]
+    fn reverse_bits() {
+        let mut storage = [0u8, 0u8];
+        storage.store(Transport::TCP).unwrap();
+        storage.store(AddressType::DomainName).unwrap();
+        assert_eq!(storage, [0b0000_1001, 0b0000_0000]);
+
+        storage.reverse_bits();
+        assert_eq!(storage, [0b0000_0000, 0b1001_0000]);
+
+        storage.reverse_bits();
+        assert_eq!(storage.get_as::<Transport>().unwrap(), Transport::TCP);
+        assert_eq!(
+            storage.get_as::<AddressType>().unwrap(),
+            AddressType::DomainName
+        );
+    }
+
+    #[test]
+    fn num_enum_interop() {
+        use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+        #[derive(Debug, PartialEq, IntoPrimitive, TryFromPrimitive)]
+        #[repr(u8)]
+        enum Priority {
+            Low = 0,
+            High = 3,
+        }
+
+        impl BitField for Priority {
+            const POS: usize = 4;
+            const WIDTH: usize = 2;
         }
+
+        let mut storage = [0u8];
+        storage.store(Priority::High).unwrap();
+        assert_eq!(storage, [0b0011_0000]);
+        assert_eq!(storage.get_as::<Priority>().unwrap(), Priority::High);
+
+        storage.store(Priority::Low).unwrap();
+        assert_eq!(storage.get_as::<Priority>().unwrap(), Priority::Low);
+
+        let corrupted = [0b0001_0000u8];
+        assert_eq!(
+            corrupted.get_as::<Priority>(),
+            Err(Error::TryFromErr {
+                raw: 0b01,
+                max: 0b11,
+                target: std::any::type_name::<Priority>(),
+            })
+        );
+    }
+
+    #[test]
+    fn validation_hook() {
+        let mut storage = [0u8];
+        storage.store(BurstLen(8)).unwrap();
+        storage.validate::<BurstLen>().unwrap();
+
+        assert_eq!(
+            storage.store(BurstLen(6)),
+            Err(Error::ValidationFailed(
+                "burst length 6 is not a power of two".into()
+            ))
+        );
+        assert_eq!(storage, [0b1000_0000]);
+
+        // Raw bits that bypassed `store` are caught by `validate`
+        let corrupted = [0b0000_0000u8];
+        assert_eq!(
+            corrupted.validate::<BurstLen>(),
+            Err(Error::ValidationFailed(
+                "burst length 0 is not a power of two".into()
+            ))
+        );
     }
 
-    impl TryFrom<u8> for Transport {
-        type Error = ();
+    #[test]
+    fn get_all() {
+        let mut storage = [0u8, 0u8];
+        storage.store(Transport::UDT).unwrap();
+        storage.store(AddressType::IPv6).unwrap();
+        storage.store(SecondByte(42)).unwrap();
+
+        let (transport, address, second) = storage
+            .get_all::<(Transport, AddressType, SecondByte)>()
+            .unwrap();
+        assert_eq!(transport, Transport::UDT);
+        assert_eq!(address, AddressType::IPv6);
+        assert_eq!(second, SecondByte(42));
+
+        let storage = [0u8, 0u8];
+        assert!(matches!(
+            storage.get_all::<(SecondByte, Transport)>(),
+            Err(Error::TryFromErr { raw: 0b00, .. })
+        ));
+    }
 
-        fn try_from(value: u8) -> Result<Self, Self::Error> {
-            match value {
-                0b01 => Ok(Transport::TCP),
-                0b10 => Ok(Transport::UDP),
-                0b11 => Ok(Transport::UDT),
-                _ => Err(()),
+    #[test]
+    fn decode_struct() {
+        let storage = [0b0000_1010u8];
+        assert_eq!(
+            storage.decode::<ActorPathHeader>().unwrap(),
+            ActorPathHeader {
+                transport: Transport::UDP,
+                address: AddressType::DomainName,
             }
-        }
+        );
+        assert!(matches!(
+            [0b0000_1100u8].decode::<ActorPathHeader>(),
+            Err(Error::TryFromErr {
+                raw: 0b00,
+                max: 0b11,
+                ..
+            })
+        ));
     }
 
-    #[derive(Debug, PartialEq)]
-    #[repr(u8)]
-    enum WideWithOffset {
-        A = 0b1111_1000,
-        B = 0b1111_1100,
-        C = 0b1111_1110,
-        D = 0b1111_1111,
+    #[test]
+    fn encode_struct() {
+        let header = ActorPathHeader {
+            transport: Transport::TCP,
+            address: AddressType::IPv6,
+        };
+        let mut storage = [0b1111_0000u8];
+        storage.encode(header).unwrap();
+        assert_eq!(storage, [0b1111_0101]);
+        assert_eq!(
+            storage.decode::<ActorPathHeader>().unwrap(),
+            ActorPathHeader {
+                transport: Transport::TCP,
+                address: AddressType::IPv6,
+            }
+        );
     }
 
-    impl BitField for WideWithOffset {
-        const POS: usize = 7;
-        const WIDTH: usize = 2;
+    #[test]
+    fn field_spans() {
+        assert_eq!(
+            <(Transport, SecondByte)>::spans(),
+            vec![
+                FieldSpan {
+                    name: "Transport",
+                    pos: 0,
+                    width: 2,
+                },
+                FieldSpan {
+                    name: "SecondByte",
+                    pos: 8,
+                    width: 8,
+                },
+            ]
+        );
     }
 
-    impl Into<u8> for WideWithOffset {
-        fn into(self) -> u8 {
-            self as u8
+    #[test]
+    fn field_name_override() {
+        mod ctrl {
+            use BitField;
+
+            #[derive(Debug)]
+            pub struct Mode;
+
+            impl BitField for Mode {
+                const POS: usize = 0;
+                const WIDTH: usize = 2;
+            }
         }
-    }
 
-    impl TryFrom<u8> for WideWithOffset {
-        type Error = ();
+        mod status {
+            use BitField;
+
+            #[derive(Debug)]
+            pub struct Mode;
 
-        fn try_from(value: u8) -> Result<Self, Self::Error> {
-            match value {
-                0b1111_1000 => Ok(WideWithOffset::A),
-                0b1111_1100 => Ok(WideWithOffset::B),
-                0b1111_1110 => Ok(WideWithOffset::C),
-                0b1111_1111 => Ok(WideWithOffset::D),
-                _ => Err(()),
+            impl BitField for Mode {
+                const POS: usize = 2;
+                const WIDTH: usize = 2;
+
+                fn name() -> &'static str {
+                    "StatusMode"
+                }
             }
         }
+
+        let names: Vec<_> = <(ctrl::Mode, status::Mode)>::spans()
+            .iter()
+            .map(|span| span.name)
+            .collect();
+        assert_eq!(names, ["Mode", "StatusMode"]);
     }
 
-    #[derive(Debug, PartialEq)]
-    #[repr(u8)]
-    enum InvalidWidth {
-        DoNotCare = 0b0,
+    #[test]
+    fn field_at_bit() {
+        type Fields = (Transport, AddressType, SecondByte);
+
+        assert_eq!(Fields::field_at_bit(1).unwrap().name, "Transport");
+        assert_eq!(Fields::field_at_bit(2).unwrap().name, "AddressType");
+        assert_eq!(Fields::field_at_bit(15).unwrap().name, "SecondByte");
+        assert_eq!(Fields::field_at_bit(4), None);
+        assert_eq!(Fields::field_at_bit(7), None);
+        assert_eq!(Fields::field_at_bit(16), None);
     }
 
-    impl BitField for InvalidWidth {
-        const POS: usize = 0;
-        const WIDTH: usize = 9; // exceeds current allowed width
+    #[test]
+    fn unused_bits() {
+        type Header = (AddressType, Transport);
+        assert_eq!(Header::trailing_unused_bits(), 0);
+        assert_eq!(Header::leading_unused_bits(8), 4);
+        assert_eq!(Header::min_storage_len(), 1);
+
+        type Padded = (SecondByte, AddressType);
+        assert_eq!(Padded::trailing_unused_bits(), 2);
+        assert_eq!(Padded::leading_unused_bits(16), 0);
+        assert_eq!(Padded::leading_unused_bits(8), 0);
+        assert_eq!(Padded::min_storage_len(), 2);
     }
 
-    impl Into<u8> for InvalidWidth {
-        fn into(self) -> u8 {
-            self as u8
-        }
+    #[test]
+    fn padding() {
+        assert_eq!(pad_to(0, 4), 0);
+        assert_eq!(pad_to(3, 4), 4);
+        assert_eq!(pad_to(4, 4), 4);
+        assert_eq!(pad_to(9, 8), 16);
+        assert_eq!(pad_to(5, 1), 5);
+    }
+
+    #[test]
+    fn masks() {
+        const ADDRESS_MASK: u64 = mask_of::<AddressType>();
+        assert_eq!(ADDRESS_MASK, 0b1100);
+        assert_eq!(shifted_mask_of::<AddressType>(), 0b11);
+        assert_eq!(mask_of::<SecondByte>(), 0xFF00);
+        assert_eq!(shifted_mask_of::<SecondByte>(), 0xFF);
+        assert_eq!(mask_of::<ThreeWide>(), 0b0111_0000);
     }
 
-    impl TryFrom<u8> for InvalidWidth {
-        type Error = ();
+    #[test]
+    fn swap_byte_order() {
+        // A big-endian 16-bit word 0x0102, with `SecondByte` defined against the
+        // little-endian layout where the high byte comes second
+        let mut storage = [0x01u8, 0x02, 0xAA, 0xBB];
+        storage.swap_byte_order(2).unwrap();
+        assert_eq!(storage, [0x02, 0x01, 0xBB, 0xAA]);
+        assert_eq!(storage.get_as::<SecondByte>().unwrap(), SecondByte(0x01));
+
+        storage.swap_byte_order(2).unwrap();
+        assert_eq!(storage, [0x01, 0x02, 0xAA, 0xBB]);
+
+        storage.swap_byte_order(4).unwrap();
+        assert_eq!(storage, [0xBB, 0xAA, 0x02, 0x01]);
+
+        let mut storage = [0x01u8, 0x02, 0x03];
+        assert_eq!(storage.swap_byte_order(2), Err(Error::InsufficientBytes));
+        assert_eq!(storage, [0x01, 0x02, 0x03]);
+    }
+
+    #[test]
+    fn very_wide_storage() {
+        /// 3-bit digest selector far past any primitive integer width
+        #[derive(Debug, PartialEq)]
+        struct Selector(u8);
+
+        impl BitField for Selector {
+            const POS: usize = 1021;
+            const WIDTH: usize = 3;
+        }
 
-        fn try_from(value: u8) -> Result<Self, Self::Error> {
-            match value {
-                0b0 => Ok(InvalidWidth::DoNotCare),
-                _ => Err(()),
+        impl From<Selector> for u8 {
+            fn from(field: Selector) -> u8 {
+                field.0
             }
         }
+
+        impl TryFrom<u8> for Selector {
+            type Error = ();
+
+            fn try_from(value: u8) -> Result<Self, Self::Error> {
+                Ok(Selector(value))
+            }
+        }
+
+        let mut descriptor = [0u8; 128];
+        descriptor.store(Transport::UDT).unwrap();
+        descriptor.store(Selector(0b101)).unwrap();
+        assert_eq!(descriptor[127], 0b1010_0000);
+        assert_eq!(descriptor.get_as::<Selector>().unwrap(), Selector(0b101));
+        assert_eq!(descriptor.get_as::<Transport>().unwrap(), Transport::UDT);
+        assert_eq!(
+            descriptor[..127].get_as::<Selector>(),
+            Err(Error::InsufficientBytes)
+        );
     }
 
     #[test]
-    fn store_and_retrieve() {
-        use super::BitFieldExt;
-        let mut storage = vec![0u8, 0u8];
-        storage.store(Transport::TCP).unwrap();
-        assert_eq!(storage.get_as::<Transport>().unwrap(), Transport::TCP);
+    fn wide_reprs() {
+        let mut storage = [0u8; 3];
+        storage.store(Length(0xABC)).unwrap();
+        assert_eq!(storage, [0xC0, 0xAB, 0x00]);
+        assert_eq!(storage.get_as::<Length>().unwrap(), Length(0xABC));
+        assert_eq!(storage.store(Length(0x1000)), Err(Error::ValueOutOfRange));
+        assert_eq!([0u8].get_as::<Length>(), Err(Error::InsufficientBytes));
+
+        let mut storage = [0xFFu8; 9];
+        storage.store(Counter(0x0123_4567_89AB_CDEF)).unwrap();
+        assert_eq!(storage[0] & 0b111, 0b111);
+        assert_eq!(storage[8] >> 3, 0b1_1111);
+        assert_eq!(
+            storage.get_as::<Counter>().unwrap(),
+            Counter(0x0123_4567_89AB_CDEF)
+        );
     }
 
     #[test]
-    fn overwrite() {
-        let mut storage = vec![0u8, 0u8];
-        storage.store(Transport::TCP).unwrap();
-        assert_eq!(storage.get_as::<Transport>().unwrap(), Transport::TCP);
-        storage.store(Transport::UDP).unwrap();
-        assert_eq!(storage.get_as::<Transport>().unwrap(), Transport::UDP);
+    fn field_wider_than_repr() {
+        #[derive(Debug)]
+        struct Oversized;
+
+        impl BitField<u16> for Oversized {
+            const POS: usize = 0;
+            const WIDTH: usize = 17;
+        }
+
+        assert_eq!(
+            BitFieldExt::<u16>::check_field::<Oversized>(&[0u8; 4][..]),
+            Err(Error::Overflow)
+        );
     }
 
     #[test]
-    fn store_too_wide() {
-        // small_storage is only 8 bits, and thus cannot fit the WideWithOffset
-        let mut small_storage = vec![0u8];
-        assert_eq!(small_storage.store(WideWithOffset::A), Err(Error::Overflow));
+    fn update() {
+        let mut storage = [0xFFu8; 9];
+        storage.store(Counter(41)).unwrap();
+        storage.update::<Counter>(|c| Counter(c.0 + 1)).unwrap();
+        assert_eq!(storage.get_as::<Counter>().unwrap(), Counter(42));
+        assert_eq!(storage[0] & 0b111, 0b111);
+
+        let mut storage = [0b0000_0001u8];
+        storage.update::<Transport>(|_| Transport::UDT).unwrap();
+        assert_eq!(storage, [0b0000_0011]);
+
+        // Out-of-range results are rejected and nothing is written
+        let mut storage = [0u8, 0u8, 0u8];
+        storage.store(Length(0xFFF)).unwrap();
+        assert_eq!(
+            storage.update::<Length>(|l| Length(l.0 + 1)),
+            Err(Error::ValueOutOfRange)
+        );
+        assert_eq!(storage.get_as::<Length>().unwrap(), Length(0xFFF));
     }
 
     #[test]
-    fn corrupted_read() {
-        // 0b0000_0000 is not a valid representation for [Transport]
-        let mut storage = vec![0u8];
-        assert_eq!(storage.get_as::<Transport>(), Err(Error::TryFromErr));
+    fn overlapping_fields() {
+        assert_eq!(
+            <(Transport, AddressType, SecondByte)>::first_overlap(),
+            None
+        );
+        assert_eq!(
+            <(Transport, AddressType, SecondByte)>::check_disjoint(),
+            Ok(())
+        );
+
+        assert_eq!(
+            <(Transport, AddressType, Straddling)>::first_overlap(),
+            Some((FieldSpan::of::<Transport>(), FieldSpan::of::<Straddling>()))
+        );
+        assert_eq!(
+            <(AddressType, Straddling)>::check_disjoint(),
+            Err(Error::FieldsOverlap(
+                FieldSpan::of::<AddressType>(),
+                FieldSpan::of::<Straddling>()
+            ))
+        );
     }
 
     #[test]
-    fn invalid_field() {
-        let mut storage = vec![0u8, 0u8];
-        // `InvalidWidth` has a width of 9, exceeding the current allowed field width
-        assert_eq!(storage.store(InvalidWidth::DoNotCare), Err(Error::Overflow));
-        assert_eq!(storage.get_as::<InvalidWidth>(), Err(Error::Overflow));
+    fn field_values() {
+        let storage = [0b0000_1001u8, 42];
+        assert_eq!(
+            storage
+                .field_values::<(SecondByte, AddressType, Transport)>()
+                .unwrap(),
+            vec![
+                (FieldSpan::of::<Transport>(), 0b01),
+                (FieldSpan::of::<AddressType>(), 0b10),
+                (FieldSpan::of::<SecondByte>(), 42),
+            ]
+        );
+        assert_eq!(
+            [0u8].field_values::<(Transport, SecondByte)>(),
+            Err(Error::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn clear_fields() {
+        let mut storage = [0xFFu8, 0xFF];
+        storage.clear_field::<AddressType>().unwrap();
+        assert_eq!(storage, [0b1111_0011, 0xFF]);
+        assert_eq!(
+            [0u8].clear_field::<SecondByte>(),
+            Err(Error::InsufficientBytes)
+        );
+
+        let mut storage = [0xFFu8, 0xFF, 0xFF];
+        storage.clear_field::<Length>().unwrap();
+        assert_eq!(storage, [0x0F, 0x00, 0xFF]);
+
+        let mut storage = [0xFFu8, 0xFF];
+        storage.clear_fields::<(Transport, SecondByte)>().unwrap();
+        assert_eq!(storage, [0b1111_1100, 0x00]);
+
+        // A field that does not fit leaves the others untouched too
+        let mut storage = [0xFFu8];
+        assert_eq!(
+            storage.clear_fields::<(Transport, SecondByte)>(),
+            Err(Error::InsufficientBytes)
+        );
+        assert_eq!(storage, [0xFF]);
+    }
+
+    #[test]
+    fn check_unknown_bits() {
+        type Fields = (Transport, AddressType, SecondByte);
+        assert_eq!([0b0000_1111u8, 0xFF].check_unknown_bits::<Fields>(), Ok(()));
+        assert_eq!(
+            [0b0101_1111u8, 0xFF].check_unknown_bits::<Fields>(),
+            Err(Error::UnknownBitsSet { bit: 4 })
+        );
+        assert_eq!(
+            [0u8, 0, 0b10].check_unknown_bits::<Fields>(),
+            Err(Error::UnknownBitsSet { bit: 17 })
+        );
+    }
+
+    #[test]
+    fn flags() {
+        let mut storage = [0u8];
+        assert_eq!(storage.get_flag::<Ack>(), Ok(false));
+        storage.set_flag::<Ack>(true).unwrap();
+        assert_eq!(storage, [0b0010_0000]);
+        assert_eq!(storage.get_flag::<Ack>(), Ok(true));
+
+        storage.toggle_flag::<Fin>().unwrap();
+        storage.toggle_flag::<Ack>().unwrap();
+        assert_eq!(storage, [0b0100_0000]);
+
+        storage.set_flags::<(Ack, Fin)>(true).unwrap();
+        assert_eq!(storage, [0b0110_0000]);
+        storage.set_flags::<(Ack, Fin)>(false).unwrap();
+        assert_eq!(storage, [0]);
+
+        // Wider fields are not flags
+        assert_eq!(storage.get_flag::<Transport>(), Err(Error::WidthMismatch));
+        assert_eq!(
+            storage.set_flags::<(Ack, Transport)>(true),
+            Err(Error::WidthMismatch)
+        );
+        assert_eq!(storage, [0]);
+    }
+
+    #[test]
+    fn set_flags_validates_before_writing() {
+        /// Once raised, the flag may not be cleared
+        #[derive(Debug)]
+        struct Sticky;
+
+        impl BitField for Sticky {
+            const POS: usize = 7;
+            const WIDTH: usize = 1;
+
+            fn validate(repr: u8) -> Result<(), String> {
+                if repr == 1 {
+                    Ok(())
+                } else {
+                    Err("sticky flag cannot be cleared".to_string())
+                }
+            }
+        }
+
+        let mut storage = [0u8];
+        storage.set_flags::<(Ack, Sticky)>(true).unwrap();
+        assert_eq!(storage, [0b1010_0000]);
+        assert_eq!(
+            storage.set_flags::<(Ack, Sticky)>(false),
+            Err(Error::ValidationFailed(
+                "sticky flag cannot be cleared".to_string()
+            ))
+        );
+        // `Ack` is left set, since nothing is written
+        assert_eq!(storage, [0b1010_0000]);
+    }
+
+    #[test]
+    fn store_with_policy() {
+        let mut storage = [0u8, 0u8];
+        assert_eq!(
+            storage.store_with(WideWithOffset::A, WidthPolicy::Strict),
+            Err(Error::ValueOutOfRange)
+        );
+        assert_eq!(storage, [0, 0]);
+
+        let mut storage = [0u8; 3];
+        storage
+            .store_with(Length(0x1ABC), WidthPolicy::Truncate)
+            .unwrap();
+        assert_eq!(storage.get_as::<Length>().unwrap(), Length(0xABC));
+        storage
+            .store_with(Length(0x1ABC), WidthPolicy::Saturate)
+            .unwrap();
+        assert_eq!(storage.get_as::<Length>().unwrap(), Length(0xFFF));
+        assert_eq!(storage, [0xF0, 0xFF, 0x00]);
+
+        // The hook sees the value that is actually written
+        let mut storage = [0u8];
+        assert_eq!(
+            storage.store_with(BurstLen(0b1_1000), WidthPolicy::Truncate),
+            Ok(())
+        );
+        assert!(matches!(
+            storage.store_with(BurstLen(0b1_1000), WidthPolicy::Saturate),
+            Err(Error::ValidationFailed(_))
+        ));
+    }
+
+    #[test]
+    fn bit_enum_width() {
+        assert_eq!(width_of::<Transport>(), Transport::WIDTH);
+        assert_eq!(width_of::<AddressType>(), 2);
     }
 }
@@ -0,0 +1,82 @@
+//! [nom] integration, so that bitfield groups can be embedded in larger nom-based protocol
+//! parsers.
+//!
+//! Enabled by the `nom` feature.
+use nom::bits::complete::take;
+use nom::error::{ErrorKind, FromExternalError, ParseError};
+use nom::IResult;
+
+use {Error, FromBitFields};
+
+/// nom's bit-level input: the remaining bytes and the bit offset into the first one.
+pub type BitInput<'a> = (&'a [u8], usize);
+
+/// A bit-level parser over nom's [BitInput] that takes `num_bits` bits and
+/// decodes a `T` from them.
+///
+/// The taken bits form an integer the way [nom::bits::complete::take] builds one, most
+/// significant bit first, and bit `n` of that integer is storage bit `n` for `T`'s fields.
+/// For example, taking the first byte of an IPv4 header as 8 bits puts the version in bits
+/// 4..8 and the IHL in bits 0..4.
+///
+/// Running out of input is reported like `take` does; a failure to decode `T` is reported as
+/// an [ErrorKind::MapRes] error carrying the crate's [Error].
+///
+/// # Panics
+/// Panics if `num_bits` exceeds 64.
+pub fn parse<'a, T, E>(num_bits: usize) -> impl Fn(BitInput<'a>) -> IResult<BitInput<'a>, T, E>
+where
+    T: FromBitFields,
+    E: ParseError<BitInput<'a>> + FromExternalError<BitInput<'a>, Error>,
+{
+    assert!(num_bits <= 64, "cannot parse more than 64 bits at once");
+    move |input| {
+        let (rest, value): (_, u64) = take(num_bits)(input)?;
+        let storage = value.to_le_bytes();
+        match T::from_fields(&storage[..num_bits.div_ceil(8)]) {
+            Ok(parsed) => Ok((rest, parsed)),
+            Err(err) => Err(nom::Err::Error(E::from_external_error(
+                input,
+                ErrorKind::MapRes,
+                err,
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixtures::*;
+
+    #[test]
+    fn nom_parser() {
+        use nom::bits::bits;
+        use nom::error::Error as NomError;
+
+        fn header(input: &[u8]) -> IResult<&[u8], (u8, ActorPathHeader, u8)> {
+            bits::<_, _, NomError<(&[u8], usize)>, _, _>(|input| {
+                let (input, marker) = take(2_usize)(input)?;
+                let (input, header) = parse(4)(input)?;
+                let (input, trailer) = take(2_usize)(input)?;
+                Ok((input, (marker, header, trailer)))
+            })(input)
+        }
+
+        // Bits in stream order: marker 11, header 1010, trailer 01, then one extra byte
+        let (rest, (marker, parsed, trailer)) = header(&[0b1110_1001, 0xFF]).unwrap();
+        assert_eq!(rest, &[0xFF]);
+        assert_eq!(marker, 0b11);
+        assert_eq!(trailer, 0b01);
+        assert_eq!(
+            parsed,
+            ActorPathHeader {
+                transport: Transport::UDP,
+                address: AddressType::DomainName,
+            }
+        );
+
+        // 0b00 is not a valid `Transport`
+        assert!(header(&[0b1110_0001]).is_err());
+    }
+}
@@ -0,0 +1,123 @@
+//! Tight packing of many equal-width values into a contiguous bitstream, such as 10-bit ADC
+//! samples, using the same LSB-first bit order as [BitField](::BitField) storages.
+use alloc::vec::Vec;
+use bit_twiddles::BITS_PER_BYTE;
+
+use {read_repr, write_repr, Error};
+
+/// Widest value supported by the packing helpers.
+pub const MAX_WIDTH: usize = 64;
+
+/// Packs `values` back to back, `width` bits each, value `i` starting at bit `i * width`.
+/// Unused bits in the last byte are zero.
+///
+/// Fails with [Error::Overflow] if `width` is 0 or above [MAX_WIDTH], and with
+/// [Error::ValueOutOfRange] if a value does not fit in `width` bits.
+pub fn pack_all<T>(values: &[T], width: usize) -> Result<Vec<u8>, Error>
+where
+    T: Copy + Into<u64>,
+{
+    check_width(width)?;
+    let mut packed = vec![0u8; (values.len() * width).div_ceil(BITS_PER_BYTE)];
+    for (i, &value) in values.iter().enumerate() {
+        let value: u64 = value.into();
+        if value.checked_shr(width as u32).unwrap_or(0) != 0 {
+            return Err(Error::ValueOutOfRange);
+        }
+        let pos = i * width;
+        for chunk in (0..width).step_by(BITS_PER_BYTE) {
+            let chunk_width = (width - chunk).min(BITS_PER_BYTE);
+            write_repr(
+                &mut packed,
+                pos + chunk,
+                chunk_width,
+                (value >> chunk) as u8,
+            );
+        }
+    }
+    Ok(packed)
+}
+
+/// Unpacks `count` values of `width` bits each, as written by [pack_all].
+///
+/// `bytes` must be exactly as long as needed for `count` values, and the unused bits of its
+/// last byte must be zero. Anything else fails with [Error::TrailingBits], while a stream
+/// too short for `count` values fails with [Error::InsufficientBytes].
+pub fn unpack_all(bytes: &[u8], width: usize, count: usize) -> Result<Vec<u64>, Error> {
+    check_width(width)?;
+    let used_bits = count * width;
+    let needed = used_bits.div_ceil(BITS_PER_BYTE);
+    if bytes.len() < needed {
+        return Err(Error::InsufficientBytes);
+    }
+    let padding = needed * BITS_PER_BYTE - used_bits;
+    if bytes.len() > needed || read_repr(bytes, used_bits, padding) != 0 {
+        return Err(Error::TrailingBits);
+    }
+
+    let values = (0..count)
+        .map(|i| {
+            (0..width)
+                .step_by(BITS_PER_BYTE)
+                .fold(0_u64, |value, chunk| {
+                    let chunk_width = (width - chunk).min(BITS_PER_BYTE);
+                    let repr = read_repr(bytes, i * width + chunk, chunk_width);
+                    value | u64::from(repr) << chunk
+                })
+        })
+        .collect();
+    Ok(values)
+}
+
+fn check_width(width: usize) -> Result<(), Error> {
+    if width == 0 || width > MAX_WIDTH {
+        Err(Error::Overflow)
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pack_samples() {
+        let samples: [u16; 3] = [0x3FF, 0x001, 0x2AA];
+        assert_eq!(
+            pack_all(&samples, 10).unwrap(),
+            vec![0xFF, 0x07, 0xA0, 0x2A]
+        );
+        assert_eq!(pack_all(&[1u8, 0, 1, 1], 1).unwrap(), vec![0b1101]);
+        assert_eq!(pack_all(&[u64::MAX], 64).unwrap(), vec![0xFF; 8]);
+        assert!(pack_all::<u8>(&[], 3).unwrap().is_empty());
+
+        assert_eq!(pack_all(&[0x400u16], 10), Err(Error::ValueOutOfRange));
+        assert_eq!(pack_all(&[0u8], 0), Err(Error::Overflow));
+        assert_eq!(pack_all(&[0u8], 65), Err(Error::Overflow));
+    }
+
+    #[test]
+    fn unpack_samples() {
+        let samples: [u16; 3] = [0x3FF, 0x001, 0x2AA];
+        let packed = pack_all(&samples, 10).unwrap();
+        assert_eq!(
+            unpack_all(&packed, 10, 3).unwrap(),
+            vec![0x3FF, 0x001, 0x2AA]
+        );
+        assert_eq!(unpack_all(&[0xFF; 8], 64, 1).unwrap(), vec![u64::MAX]);
+        assert!(unpack_all(&[], 7, 0).unwrap().is_empty());
+
+        assert_eq!(unpack_all(&packed, 10, 4), Err(Error::InsufficientBytes));
+        // Two padding bits are set
+        assert_eq!(
+            unpack_all(&[0xFF, 0x07, 0xA0, 0xEA], 10, 3),
+            Err(Error::TrailingBits)
+        );
+        assert_eq!(
+            unpack_all(&[0xFF, 0x07, 0xA0, 0x2A, 0x00], 10, 3),
+            Err(Error::TrailingBits)
+        );
+        assert_eq!(unpack_all(&packed, 0, 3), Err(Error::Overflow));
+    }
+}
@@ -0,0 +1,89 @@
+//! Moving field values between two layouts, e.g. protocol v1 and v2 headers.
+//!
+//! Fields are matched by [FieldSpan::name](::FieldSpan::name), so `v1::Transport` maps onto
+//! `v2::Transport` wherever each version places it.
+use alloc::vec::Vec;
+
+use {check_span, write_repr, Error, FieldSpans, WidthPolicy};
+
+/// Copies every field of `Src` found in `src` into the same-named field of `Dst` in `dst`.
+///
+/// Fields present on only one side are skipped, leaving their bits in `dst` untouched.
+/// Nothing is written unless every matched field can be remapped. Representations are copied
+/// raw, without running [BitField::validate](::BitField::validate) on the `Dst` fields.
+pub fn remap<Src, Dst>(src: &[u8], dst: &mut [u8], policy: WidthPolicy) -> Result<(), Error>
+where
+    Src: FieldSpans,
+    Dst: FieldSpans,
+{
+    let targets = Dst::spans();
+    let mut writes = Vec::new();
+    for from in Src::spans() {
+        let to = match targets.iter().find(|to| to.name == from.name) {
+            Some(to) => to,
+            None => continue,
+        };
+        check_span(dst, to.pos, to.width)?;
+        let repr = policy.fit(from.read(src)?.into(), to.width)? as u8;
+        writes.push((to, repr));
+    }
+    for (to, repr) in writes {
+        write_repr(dst, to.pos, to.width, repr);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixtures::*;
+
+    #[test]
+    fn remap_between_layouts() {
+        mod v2 {
+            use BitField;
+
+            /// Moved up and narrowed to a single bit
+            #[derive(Debug)]
+            pub struct AddressType;
+
+            impl BitField for AddressType {
+                const POS: usize = 7;
+                const WIDTH: usize = 1;
+            }
+
+            /// Widened and moved into the second byte
+            #[derive(Debug)]
+            pub struct Transport;
+
+            impl BitField for Transport {
+                const POS: usize = 8;
+                const WIDTH: usize = 4;
+            }
+        }
+
+        type V1 = (Transport, AddressType, SecondByte);
+        type V2 = (v2::Transport, v2::AddressType);
+
+        let v1 = [0b0000_0111u8, 0xAB];
+        let mut out = [0u8, 0xF0];
+        remap::<V1, V2>(&v1, &mut out, WidthPolicy::Strict).unwrap();
+        assert_eq!(out, [0b1000_0000, 0xF3]);
+
+        // DomainName (0b10) does not fit in one bit
+        let v1 = [0b0000_1011u8, 0xAB];
+        let mut out = [0u8, 0u8];
+        assert_eq!(
+            remap::<V1, V2>(&v1, &mut out, WidthPolicy::Strict),
+            Err(Error::ValueOutOfRange)
+        );
+        assert_eq!(out, [0u8, 0u8]);
+        remap::<V1, V2>(&v1, &mut out, WidthPolicy::Truncate).unwrap();
+        assert_eq!(out, [0b0000_0000, 0x03]);
+
+        assert_eq!(
+            remap::<V1, V2>(&v1, &mut [0u8], WidthPolicy::Truncate),
+            Err(Error::InsufficientBytes)
+        );
+    }
+}
@@ -0,0 +1,111 @@
+//! Runs of consecutive set or unset bits in a storage.
+use bit_twiddles::{byte_bit_offset, test_bit};
+
+/// A maximal run of equal bits, `len` bits long starting at storage bit `start`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BitRun {
+    pub start: usize,
+    pub len: usize,
+    pub set: bool,
+}
+
+/// Iterator over the [BitRun]s in a range of storage bits, in ascending bit order.
+///
+/// Created by [StorageExt::bit_runs](::StorageExt::bit_runs).
+#[derive(Clone, Debug)]
+pub struct BitRuns<'a> {
+    storage: &'a [u8],
+    next: usize,
+    end: usize,
+}
+
+impl<'a> BitRuns<'a> {
+    /// Runs within bits `start..end` of `storage`.
+    ///
+    /// # Panics
+    /// Iterating panics if `end` exceeds the number of bits in `storage`.
+    pub(crate) fn new(storage: &'a [u8], start: usize, end: usize) -> BitRuns<'a> {
+        BitRuns {
+            storage,
+            next: start,
+            end,
+        }
+    }
+
+    fn bit(&self, idx: usize) -> bool {
+        let (byte, bit) = byte_bit_offset(idx);
+        test_bit(&self.storage[byte], bit)
+    }
+}
+
+impl<'a> Iterator for BitRuns<'a> {
+    type Item = BitRun;
+
+    fn next(&mut self) -> Option<BitRun> {
+        if self.next >= self.end {
+            return None;
+        }
+        let start = self.next;
+        let set = self.bit(start);
+        let mut idx = start + 1;
+        while idx < self.end && self.bit(idx) == set {
+            idx += 1;
+        }
+        self.next = idx;
+        Some(BitRun {
+            start,
+            len: idx - start,
+            set,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+    use fixtures::*;
+    use {Error, StorageExt};
+
+    #[test]
+    fn bit_runs() {
+        let run = |start, len, set| BitRun { start, len, set };
+
+        let storage = [0b1110_0001u8, 0b0000_0001];
+        assert_eq!(
+            storage.bit_runs().collect::<Vec<_>>(),
+            vec![
+                run(0, 1, true),
+                run(1, 4, false),
+                run(5, 4, true),
+                run(9, 7, false),
+            ]
+        );
+        assert_eq!(
+            storage
+                .bit_runs()
+                .filter(|run| run.set)
+                .map(|run| run.len)
+                .sum::<usize>(),
+            5
+        );
+        assert_eq!([0u8; 0].bit_runs().next(), None);
+
+        assert_eq!(
+            storage
+                .field_bit_runs::<(Transport, AddressType, SecondByte)>()
+                .unwrap(),
+            vec![
+                run(0, 1, true),
+                run(1, 1, false),
+                run(2, 2, false),
+                run(8, 1, true),
+                run(9, 7, false),
+            ]
+        );
+        assert_eq!(
+            [0u8].field_bit_runs::<(Transport, SecondByte)>(),
+            Err(Error::InsufficientBytes)
+        );
+    }
+}
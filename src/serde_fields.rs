@@ -0,0 +1,85 @@
+//! `serde` support, behind the `serde` feature.
+//!
+//! Storages already serialize as their bytes. [FieldMap] serializes the decoded fields instead,
+//! and [FieldSpan] serializes as its name, position and width for publishing a
+//! layout. Layouts are types, so they have no `Deserialize` counterpart.
+use core::marker::PhantomData;
+use serde::ser::{Error as _, SerializeMap, SerializeStruct};
+use serde::{Serialize, Serializer};
+
+use {FieldSpan, FieldSpans};
+
+/// Serializes the raw values of `Fields` in a storage as a map from field name to value, the
+/// same shape as [to_json_object](::jsonl::to_json_object).
+///
+/// A storage too short for the fields fails serialization with a custom error.
+#[derive(Debug)]
+pub struct FieldMap<'a, Fields> {
+    storage: &'a [u8],
+    fields: PhantomData<Fields>,
+}
+
+impl<'a, Fields: FieldSpans> FieldMap<'a, Fields> {
+    pub fn new(storage: &'a [u8]) -> Self {
+        FieldMap {
+            storage,
+            fields: PhantomData,
+        }
+    }
+}
+
+impl<Fields: FieldSpans> Serialize for FieldMap<'_, Fields> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let spans = Fields::spans();
+        let mut map = serializer.serialize_map(Some(spans.len()))?;
+        for span in spans {
+            let raw = span
+                .read(self.storage)
+                .map_err(|err| S::Error::custom(format_args!("{:?}", err)))?;
+            map.serialize_entry(span.name, &raw)?;
+        }
+        map.end()
+    }
+}
+
+impl Serialize for FieldSpan {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut span = serializer.serialize_struct("FieldSpan", 3)?;
+        span.serialize_field("name", self.name)?;
+        span.serialize_field("pos", &self.pos)?;
+        span.serialize_field("width", &self.width)?;
+        span.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use fixtures::*;
+
+    type Fields = (Transport, AddressType, SecondByte);
+
+    #[test]
+    fn field_map_serializes_as_map() {
+        let storage = [0b0000_1001u8, 7];
+        assert_eq!(
+            serde_json::to_string(&FieldMap::<Fields>::new(&storage)).unwrap(),
+            r#"{"Transport":1,"AddressType":2,"SecondByte":7}"#
+        );
+    }
+
+    #[test]
+    fn field_map_rejects_short_storage() {
+        let err = serde_json::to_string(&FieldMap::<Fields>::new(&[0u8])).unwrap_err();
+        assert_eq!(err.to_string(), "InsufficientBytes");
+    }
+
+    #[test]
+    fn field_span_serializes_as_struct() {
+        assert_eq!(
+            serde_json::to_string(&FieldSpan::of::<SecondByte>()).unwrap(),
+            r#"{"name":"SecondByte","pos":8,"width":8}"#
+        );
+    }
+}
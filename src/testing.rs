@@ -0,0 +1,107 @@
+//! Conformance helpers for crates defining their own [BitField] types.
+//!
+//! These are meant to be called from downstream `#[test]` functions and panic on failure.
+//! See [wide fields](crate#wide-fields).
+use core::convert::TryFrom;
+use core::fmt::Debug;
+
+use {read_repr, write_repr, BitField, BitFieldExt};
+
+/// Number of bytes needed for a storage that can hold `Field`.
+fn storage_len<Field: BitField<u8>>() -> usize {
+    (Field::POS + Field::WIDTH).div_ceil(8)
+}
+
+/// Stores each of `values` in a zeroed storage and asserts that reading it back yields an
+/// equal value.
+///
+/// # Panics
+/// Panics if a value cannot be stored or read back, or if it decodes to a different value.
+pub fn assert_roundtrip<Field, I>(values: I)
+where
+    Field: BitField<u8> + Into<u8> + TryFrom<u8> + Clone + PartialEq + Debug,
+    I: IntoIterator<Item = Field>,
+{
+    for value in values {
+        let mut storage = vec![0u8; storage_len::<Field>()];
+        if let Err(err) = storage.store(value.clone()) {
+            panic!("failed to store {:?}: {:?}", value, err);
+        }
+        assert_eq!(
+            storage.get_as::<Field>(),
+            Ok(value),
+            "round-trip mismatch, storage is {:?}",
+            storage
+        );
+    }
+}
+
+/// Walks every representation that fits in `Field::WIDTH` bits and, for each one `Field`
+/// accepts, asserts that encoding the decoded value writes back exactly the same bits
+/// without touching any bits outside the field.
+///
+/// This is done on both an all-zeroes and an all-ones storage.
+///
+/// # Panics
+/// Panics if `Field` is not a valid field, or on the first representation that fails to
+/// round-trip.
+pub fn assert_all_reprs_roundtrip<Field>()
+where
+    Field: BitField<u8> + Into<u8> + TryFrom<u8> + Debug,
+{
+    let len = storage_len::<Field>();
+    if let Err(err) = vec![0u8; len].check_field::<Field>() {
+        panic!("invalid field: {:?}", err);
+    }
+
+    for background in [0x00u8, 0xFF] {
+        for repr in 0..(1u16 << Field::WIDTH) {
+            let repr = repr as u8;
+            let field = match Field::try_from(repr) {
+                Ok(field) => field,
+                Err(_) => continue,
+            };
+
+            let mut expected = vec![background; len];
+            write_repr(&mut expected, Field::POS, Field::WIDTH, repr);
+
+            let mut storage = vec![background; len];
+            storage.store(field).unwrap();
+            assert_eq!(
+                storage, expected,
+                "storing representation {:#b} wrote unexpected bits",
+                repr
+            );
+
+            let decoded = storage.get_as::<Field>().unwrap();
+            let reencoded: u8 = decoded.into();
+            assert_eq!(
+                read_repr(&[reencoded], 0, Field::WIDTH),
+                repr,
+                "representation {:#b} did not survive decode and re-encode",
+                repr
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixtures::*;
+
+    #[test]
+    fn testing_helpers() {
+        assert_roundtrip(vec![Transport::TCP, Transport::UDP, Transport::UDT]);
+        assert_roundtrip(vec![AddressType::IPv4, AddressType::DomainName]);
+        assert_all_reprs_roundtrip::<Transport>();
+        assert_all_reprs_roundtrip::<AddressType>();
+    }
+
+    #[test]
+    #[should_panic]
+    fn testing_helpers_catch_lossy_field() {
+        // `WideWithOffset` representations do not fit in its 2-bit width
+        assert_roundtrip(vec![WideWithOffset::A]);
+    }
+}
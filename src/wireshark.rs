@@ -0,0 +1,229 @@
+//! Generation of a basic Wireshark dissector in Lua, so that captures carrying a storage can
+//! be inspected without hand-writing one.
+use alloc::string::String;
+use alloc::vec::Vec;
+use kaitai::snake_case;
+
+use bit_twiddles::BITS_PER_BYTE;
+
+use {end_bit, max_bits, Error, FieldSpans};
+
+/// Generates a Lua dissector for a protocol named `name` (used for the filter prefix, e.g.
+/// `actor_path.transport`), described as `description` in the packet tree.
+///
+/// Every field is declared on the smallest little-endian integer covering its bytes, with a
+/// bitmask selecting its bits, and shown with its raw value. Register the result as a Wireshark
+/// plugin and bind `<name>_proto` to a port or heuristic in `DissectorTable` as needed.
+/// Zero-width fields carry no bits and are left out.
+///
+/// `name` and `description` are escaped where they appear in Lua strings. Lua identifiers use
+/// `name` in snake case instead, so `my-proto` is registered as `my_proto_proto`.
+///
+/// Fails with [Error::Overflow] for fields wider than 8 bits, as the accessors do, and with
+/// [Error::DuplicateIdentifier] if two fields have the same name in snake case, since
+/// Wireshark needs a distinct filter name for each.
+pub fn to_lua_dissector<Fields: FieldSpans>(
+    name: &str,
+    description: &str,
+) -> Result<String, Error> {
+    let spans = Fields::spans();
+    let total_len = end_bit(&spans).div_ceil(8);
+    let ident = lua_identifier(name);
+
+    let mut declarations = String::new();
+    let mut registrations = Vec::new();
+    let mut additions = String::new();
+    for span in spans.iter().filter(|span| span.width > 0) {
+        if span.width > BITS_PER_BYTE {
+            return Err(Error::Overflow);
+        }
+        let id = snake_case(span.name);
+        let field = format!("f_{}", id);
+        if registrations.contains(&field) {
+            return Err(Error::DuplicateIdentifier(id));
+        }
+        let first = span.pos / 8;
+        let len = (span.pos + span.width).div_ceil(8) - first;
+        let mask = max_bits(span.width) << (span.pos - 8 * first);
+        // A field of at most 8 bits covers one or two bytes
+        let (ty, mask) = match len {
+            1 => ("uint8", format!("0x{:02x}", mask)),
+            _ => ("uint16", format!("0x{:04x}", mask)),
+        };
+        declarations.push_str(&format!(
+            "local f_{id} = ProtoField.{ty}(\"{name}.{id}\", \"{label}\", base.DEC, nil, {mask})\n",
+            id = id,
+            ty = ty,
+            name = lua_escape(name),
+            label = lua_escape(span.name),
+            mask = mask
+        ));
+        additions.push_str(&format!(
+            "    subtree:add_le({}, buffer({}, {}))\n",
+            field, first, len
+        ));
+        registrations.push(field);
+    }
+
+    Ok(format!(
+        "local {ident}_proto = Proto(\"{name}\", \"{description}\")
+
+{declarations}
+{ident}_proto.fields = {{ {registrations} }}
+
+function {ident}_proto.dissector(buffer, pinfo, tree)
+    if buffer:len() < {total_len} then return 0 end
+    pinfo.cols.protocol = \"{upper}\"
+    local subtree = tree:add({ident}_proto, buffer(0, {total_len}))
+{additions}    return {total_len}
+end
+",
+        ident = ident,
+        name = lua_escape(name),
+        description = lua_escape(description),
+        declarations = declarations,
+        registrations = registrations.join(", "),
+        total_len = total_len,
+        upper = lua_escape(&name.to_uppercase()),
+        additions = additions
+    ))
+}
+
+/// `name` in snake case, with a leading `_` if it would otherwise start with a digit.
+fn lua_identifier(name: &str) -> String {
+    let ident = snake_case(name);
+    if ident.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", ident)
+    } else {
+        ident
+    }
+}
+
+/// Escapes `text` for use inside a double-quoted Lua string.
+fn lua_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c if c.is_ascii_control() => escaped.push_str(&format!("\\{:03}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use fixtures::*;
+    use BitField;
+
+    #[test]
+    fn wireshark_dissector() {
+        /// Straddles the byte boundary
+        #[derive(Debug)]
+        struct Window;
+
+        impl BitField for Window {
+            const POS: usize = 6;
+            const WIDTH: usize = 4;
+        }
+
+        assert_eq!(
+            to_lua_dissector::<(Transport, Window)>("actor_path", "Actor path header").unwrap(),
+            r#"local actor_path_proto = Proto("actor_path", "Actor path header")
+
+local f_transport = ProtoField.uint8("actor_path.transport", "Transport", base.DEC, nil, 0x03)
+local f_window = ProtoField.uint16("actor_path.window", "Window", base.DEC, nil, 0x03c0)
+
+actor_path_proto.fields = { f_transport, f_window }
+
+function actor_path_proto.dissector(buffer, pinfo, tree)
+    if buffer:len() < 2 then return 0 end
+    pinfo.cols.protocol = "ACTOR_PATH"
+    local subtree = tree:add(actor_path_proto, buffer(0, 2))
+    subtree:add_le(f_transport, buffer(0, 1))
+    subtree:add_le(f_window, buffer(0, 2))
+    return 2
+end
+"#
+        );
+    }
+
+    #[test]
+    fn wireshark_dissector_escapes_names() {
+        /// Claims no bits at all
+        #[derive(Debug)]
+        struct Marker;
+
+        impl BitField for Marker {
+            const POS: usize = 8;
+            const WIDTH: usize = 0;
+        }
+
+        assert_eq!(
+            to_lua_dissector::<(Transport, Marker)>("my-proto", "he said \"hi\"").unwrap(),
+            r#"local my_proto_proto = Proto("my-proto", "he said \"hi\"")
+
+local f_transport = ProtoField.uint8("my-proto.transport", "Transport", base.DEC, nil, 0x03)
+
+my_proto_proto.fields = { f_transport }
+
+function my_proto_proto.dissector(buffer, pinfo, tree)
+    if buffer:len() < 1 then return 0 end
+    pinfo.cols.protocol = "MY-PROTO"
+    local subtree = tree:add(my_proto_proto, buffer(0, 1))
+    subtree:add_le(f_transport, buffer(0, 1))
+    return 1
+end
+"#
+        );
+        assert_eq!(lua_identifier("3com"), "_3com");
+        assert_eq!(lua_escape("a\\b\n\t"), "a\\\\b\\n\\009");
+    }
+
+    #[test]
+    fn wireshark_dissector_rejects_invalid_fields() {
+        mod v2 {
+            use BitField;
+
+            /// Same name as the v1 field, one byte further up
+            #[derive(Debug)]
+            pub struct Transport;
+
+            impl BitField for Transport {
+                const POS: usize = 8;
+                const WIDTH: usize = 2;
+            }
+        }
+
+        /// Wider than its `u8` representation
+        #[derive(Debug)]
+        struct Wide;
+
+        impl BitField for Wide {
+            const POS: usize = 0;
+            const WIDTH: usize = 30;
+        }
+
+        /// Wider than any mask
+        #[derive(Debug)]
+        struct Huge;
+
+        impl BitField for Huge {
+            const POS: usize = 0;
+            const WIDTH: usize = 200;
+        }
+
+        assert_eq!(
+            to_lua_dissector::<(Transport, v2::Transport)>("p", "p"),
+            Err(Error::DuplicateIdentifier("transport".to_string()))
+        );
+        assert_eq!(to_lua_dissector::<(Wide,)>("p", "p"), Err(Error::Overflow));
+        assert_eq!(to_lua_dissector::<(Huge,)>("p", "p"), Err(Error::Overflow));
+    }
+}